@@ -0,0 +1,137 @@
+// Helpers generating random values, shared by the blueprints of the different days.
+// Blueprints use `Runtime::generate_uuid` as source of randomness:
+// `random::range(&mut Runtime::generate_uuid, 0, 10)`.
+// WARNING: the uuids can be predicted, DON'T USE THIS IN PRODUCTION !
+
+// Provides uniformly distributed random numbers
+pub trait RandomSource {
+    fn next_u128(&mut self) -> u128;
+}
+
+// Allow any function or closure returning an u128, like `Runtime::generate_uuid`,
+// to be used as source
+impl<F: FnMut() -> u128> RandomSource for F {
+    fn next_u128(&mut self) -> u128 {
+        self()
+    }
+}
+
+// Return a random number between min (included) and max (excluded).
+// Values above the largest multiple of the range are discarded so that
+// every number has the same chance of being picked.
+pub fn range<S: RandomSource>(source: &mut S, min: u64, max: u64) -> u64 {
+    assert!(min < max, "The range is empty !");
+
+    let size = (max - min) as u128;
+    let limit = u128::MAX - u128::MAX % size;
+    loop {
+        let value = source.next_u128();
+        if value < limit {
+            return min + (value % size) as u64;
+        }
+    }
+}
+
+// Reorder the items randomly with the Fisher-Yates algorithm
+pub fn shuffle<S: RandomSource, T>(source: &mut S, items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = range(source, 0, i as u64 + 1) as usize;
+        items.swap(i, j);
+    }
+}
+
+// Return the index of a randomly picked item.
+// Each index is picked with a probability proportional to its weight.
+pub fn weighted_choice<S: RandomSource>(source: &mut S, weights: &[u64]) -> usize {
+    let total: u64 = weights.iter().sum();
+    assert!(total > 0, "At least one weight must be positive !");
+
+    let mut value = range(source, 0, total);
+    for (i, weight) in weights.iter().enumerate() {
+        if value < *weight {
+            return i;
+        }
+        value -= weight;
+    }
+
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic xorshift generator, standing in for the ledger's uuids
+    struct XorShift(u128);
+
+    impl RandomSource for XorShift {
+        fn next_u128(&mut self) -> u128 {
+            self.0 ^= self.0 << 35;
+            self.0 ^= self.0 >> 21;
+            self.0 ^= self.0 << 4;
+            self.0
+        }
+    }
+
+    fn source() -> XorShift {
+        XorShift(0x2545_f491_4f6c_dd1d_9e37_79b9_7f4a_7c15)
+    }
+
+    // Make sure every count is within 5% of the expected one
+    fn assert_close(counts: &[u64], expected: &[f64]) {
+        for (count, expected) in counts.iter().zip(expected) {
+            let error = (*count as f64 - expected).abs() / expected;
+            assert!(error < 0.05, "count {} too far from {}", count, expected);
+        }
+    }
+
+    #[test]
+    fn range_is_uniform() {
+        let mut source = source();
+        let mut counts = [0u64; 6];
+        for _ in 0..60000 {
+            let value = range(&mut source, 10, 16);
+            assert!((10..16).contains(&value));
+            counts[(value - 10) as usize] += 1;
+        }
+
+        assert_close(&counts, &[10000.0; 6]);
+    }
+
+    #[test]
+    fn range_discards_biased_values() {
+        // u128::MAX is a multiple of 3, so it is the first rejected value
+        let mut values = vec![u128::MAX, 5].into_iter();
+        let mut source = || values.next().unwrap();
+
+        assert_eq!(range(&mut source, 0, 3), 2);
+    }
+
+    #[test]
+    fn shuffle_permutations_are_uniform() {
+        let mut source = source();
+        let permutations = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+        let mut counts = [0u64; 6];
+        for _ in 0..60000 {
+            let mut items = vec![0, 1, 2];
+            shuffle(&mut source, &mut items);
+            let index = permutations.iter().position(|permutation| items == permutation).unwrap();
+            counts[index] += 1;
+        }
+
+        assert_close(&counts, &[10000.0; 6]);
+    }
+
+    #[test]
+    fn weighted_choice_follows_weights() {
+        let mut source = source();
+        let weights = [1, 0, 2, 7];
+        let mut counts = [0u64; 4];
+        for _ in 0..100000 {
+            counts[weighted_choice(&mut source, &weights)] += 1;
+        }
+
+        assert_eq!(counts[1], 0);
+        assert_close(&[counts[0], counts[2], counts[3]], &[10000.0, 20000.0, 70000.0]);
+    }
+}
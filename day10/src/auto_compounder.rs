@@ -0,0 +1,155 @@
+use scrypto::prelude::*;
+use crate::coal_yield_farming::{CoalYieldFarming, StakerData};
+
+// Stakes the deposited coal into a CoalYieldFarming component
+// and restakes the rewards, so that users don't have to
+// claim and restake them manually every epoch.
+blueprint! {
+    struct CoalAutoCompounder {
+        // Address of the CoalYieldFarming component
+        farm: ComponentAddress,
+        // Will hold the staking position of this component on the farm
+        position: Vault,
+        // Badge allowing the component to mint and burn shares
+        share_minter: Vault,
+        // Resource definition of the share tokens
+        share_def: ResourceAddress,
+        // Fraction of the harvested rewards sent to the caller of `harvest`
+        harvest_bounty: Decimal
+    }
+
+    impl CoalAutoCompounder {
+        pub fn new(farm_address: ComponentAddress, harvest_bounty: Decimal) -> ComponentAddress {
+            assert!(harvest_bounty >= Decimal::zero() && harvest_bounty < Decimal::one(), "The bounty must be between 0 and 1 !");
+
+            let farm: CoalYieldFarming = farm_address.into();
+
+            // Badge allowing this component to mint and burn shares
+            let share_minter = ResourceBuilder::new_fungible()
+                                .divisibility(DIVISIBILITY_NONE)
+                                .metadata("name", "Coal Share Minter")
+                                .initial_supply(1);
+
+            // Shares represent a portion of the coal staked by this component
+            let share_def = ResourceBuilder::new_fungible()
+                                .metadata("name", "Compounded Coal Share")
+                                .mintable(rule!(require(share_minter.resource_address())), LOCKED)
+                                .burnable(rule!(require(share_minter.resource_address())), LOCKED)
+                                .no_initial_supply();
+
+            Self {
+                farm: farm_address,
+                position: Vault::new(farm.get_position_def()),
+                share_minter: Vault::with_bucket(share_minter),
+                share_def: share_def,
+                harvest_bounty: harvest_bounty
+            }
+            .instantiate().globalize()
+        }
+
+        // Deposit coal and receive shares in return.
+        // The coal is staked on the farm right away.
+        pub fn deposit(&mut self, coal: Bucket) -> Bucket {
+            // Restake the pending rewards first so that
+            // the new shares are priced correctly
+            let rewards = self.claim_rewards();
+            self.restake(rewards);
+
+            let total_shares = borrow_resource_manager!(self.share_def).total_supply();
+            let shares = if total_shares == Decimal::zero() {
+                coal.amount()
+            } else {
+                coal.amount() * total_shares / self.total_staked()
+            };
+
+            self.restake(coal);
+
+            self.share_minter.authorize(|| {
+                borrow_resource_manager!(self.share_def).mint(shares)
+            })
+        }
+
+        // Burn the shares and return the corresponding coal
+        pub fn redeem(&mut self, shares: Bucket) -> Bucket {
+            assert!(shares.resource_address() == self.share_def, "Wrong share tokens !");
+
+            let rewards = self.claim_rewards();
+            self.restake(rewards);
+
+            let total_shares = borrow_resource_manager!(self.share_def).total_supply();
+            let amount = shares.amount() * self.total_staked() / total_shares;
+
+            self.share_minter.authorize(|| {
+                shares.burn()
+            });
+
+            let farm: CoalYieldFarming = self.farm.into();
+            farm.unstake(amount, self.position.create_proof())
+        }
+
+        // Claim the rewards from the farm and restake them.
+        // The caller receives a small part of the rewards for their trouble.
+        pub fn harvest(&mut self) -> Bucket {
+            let mut rewards = self.claim_rewards();
+            let bounty = rewards.take(rewards.amount() * self.harvest_bounty);
+
+            info!("Compounding {} coal", rewards.amount());
+            self.restake(rewards);
+
+            bounty
+        }
+
+        // Return the amount of coal a single share can be redeemed for
+        pub fn get_share_price(&self) -> Decimal {
+            let total_shares = borrow_resource_manager!(self.share_def).total_supply();
+            if total_shares == Decimal::zero() {
+                Decimal::one()
+            } else {
+                self.total_staked() / total_shares
+            }
+        }
+
+        // Amount of coal staked on the farm by this component,
+        // read from the position so that it always matches the farm
+        fn total_staked(&self) -> Decimal {
+            if self.position.is_empty() {
+                return Decimal::zero();
+            }
+
+            self.position.non_fungible::<StakerData>().data().amount
+        }
+
+        fn claim_rewards(&self) -> Bucket {
+            let farm: CoalYieldFarming = self.farm.into();
+            if self.position.is_empty() {
+                // Nothing staked yet, so no rewards
+                return Bucket::new(farm.get_coal_resource());
+            }
+
+            farm.claim_rewards(self.position.create_proof())
+        }
+
+        // Stake the coal on the farm, without lock
+        // so that shares can be redeemed at any time
+        fn restake(&mut self, coal: Bucket) {
+            if coal.is_empty() {
+                return;
+            }
+
+            let farm: CoalYieldFarming = self.farm.into();
+
+            if self.position.is_empty() {
+                // Make sure the stake won't be locked, or redeeming would burn coal
+                let unlocked = match farm.get_tiers().get(&0) {
+                    Some(tier) => tier.lock_epochs == 0,
+                    None => false
+                };
+                assert!(unlocked, "The farm's tier 0 must not lock the stakes !");
+
+                self.position.put(farm.stake(coal, 0));
+            } else {
+                farm.add_to_stake(coal, self.position.create_proof());
+            }
+        }
+    }
+}
@@ -0,0 +1,524 @@
+use scrypto::prelude::*;
+use sbor::*;
+
+// Lock period chosen at stake time and the reward
+// multiplier applied to the stakes locked for that period
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub struct LockTier {
+    pub(crate) lock_epochs: u64,
+    multiplier: Decimal
+}
+
+// Used to keep track of the user's stake.
+// Stored as the data of the staking position NFT.
+#[derive(NonFungibleData)]
+pub struct StakerData {
+    // Define when the user staked
+    started_at: u64,
+    // Defines the amount that the user staked
+    #[scrypto(mutable)]
+    pub(crate) amount: Decimal,
+    // Epoch before which withdrawing incurs a penalty
+    #[scrypto(mutable)]
+    lock_until: u64,
+    // Reward multiplier of the lock tier chosen at stake time
+    multiplier: Decimal,
+    // Value of the reward per token accumulator the last
+    // time the rewards of this stake were computed
+    #[scrypto(mutable)]
+    reward_debt: Decimal,
+    // Rewards earned but not yet claimed
+    #[scrypto(mutable)]
+    pending_rewards: Decimal
+}
+
+// Badge identifying the users of the faucet
+#[derive(NonFungibleData)]
+pub struct FaucetUserData {
+    // Last epoch the user received coal from the faucet
+    #[scrypto(mutable)]
+    last_claim_epoch: Option<u64>
+}
+
+blueprint! {
+    struct CoalYieldFarming {
+        // Will hold a badge allowing the component to
+        // mint Coal tokens and mint, update and burn staking positions
+        minter: Vault,
+
+        // Will hold the staked Coal tokens
+        stake_pool: Vault,
+
+        // Resource definition of the staking position NFTs
+        position_def: ResourceAddress,
+
+        // Number of staking positions minted
+        nb_positions: u64,
+
+        // Maps tier number to its lock period and reward multiplier
+        tiers: HashMap<u8, LockTier>,
+
+        // Fraction of the stake burned when withdrawing before the lock expires
+        early_exit_penalty: Decimal,
+
+        // Sum of the staked amounts weighted by their tier multiplier
+        total_weight: Decimal,
+
+        // Amount of coal rewarded each epoch before the first halving,
+        // shared between all stakers in proportion to their stake
+        initial_emission: Decimal,
+
+        // Number of epochs after which the emission rate is halved
+        halving_interval: u64,
+
+        // Epoch the emission schedule started
+        start_epoch: u64,
+
+        // Maximum amount of coal that can ever be emitted
+        max_supply: Decimal,
+
+        // Coal emitted so far, either as staking rewards or by the faucet
+        total_emitted: Decimal,
+
+        // Resource definition of the faucet user badges
+        faucet_badge_def: ResourceAddress,
+
+        // Number of faucet badges minted
+        nb_faucet_users: u64,
+
+        // Whether the faucet is enabled
+        faucet_enabled: bool,
+
+        // Total rewards earned by one weighted staked coal token since the start
+        reward_per_token: Decimal,
+
+        // Last epoch the reward per token accumulator was updated
+        last_update_epoch: u64
+    }
+
+    impl CoalYieldFarming {
+        pub fn new(initial_emission: Decimal, halving_interval: u64, max_supply: Decimal) -> (ComponentAddress, Bucket) {
+            assert!(halving_interval > 0, "The halving interval must be at least one epoch !");
+
+            // Create the minter badge.
+            // this badge will be owned by the component and will
+            // allow it to mint new coal tokens and manage the staking positions
+            let minter = ResourceBuilder::new_fungible()
+                                .divisibility(DIVISIBILITY_NONE)
+                                .metadata("name", "Coal Minter Badge")
+                                .initial_supply(1);
+
+            // Create the admin badge, allowing to configure the lock tiers and the faucet
+            let admin_badge = ResourceBuilder::new_fungible()
+                                .divisibility(DIVISIBILITY_NONE)
+                                .metadata("name", "CoalYieldFarming Admin")
+                                .initial_supply(1);
+
+            // Define the coal resource.
+            // Coal is burned when stakers exit before the end of their lock.
+            let coal = ResourceBuilder::new_fungible()
+                        .metadata("name", "Coal")
+                        .mintable(rule!(require(minter.resource_address())), LOCKED)
+                        .burnable(rule!(require(minter.resource_address())), LOCKED)
+                        .no_initial_supply();
+
+            // Default lock tiers: no lock, 10 epochs and 50 epochs
+            let mut tiers: HashMap<u8, LockTier> = HashMap::new();
+            tiers.insert(0, LockTier { lock_epochs: 0, multiplier: Decimal::one() });
+            tiers.insert(1, LockTier { lock_epochs: 10, multiplier: dec!("1.5") });
+            tiers.insert(2, LockTier { lock_epochs: 50, multiplier: dec!("3") });
+
+            // Define the staking position NFT.
+            // All the stakes are represented by this single resource
+            // and can be freely transferred between accounts.
+            let position_def = ResourceBuilder::new_non_fungible()
+                        .metadata("name", "Coal Stake Position")
+                        .mintable(rule!(require(minter.resource_address())), LOCKED)
+                        .burnable(rule!(require(minter.resource_address())), LOCKED)
+                        .updateable_non_fungible_data(rule!(require(minter.resource_address())), LOCKED)
+                        .no_initial_supply();
+
+            // Define the badge identifying the faucet users.
+            // The faucet can only be used once per epoch with each badge.
+            let faucet_badge_def = ResourceBuilder::new_non_fungible()
+                        .metadata("name", "Coal Faucet Badge")
+                        .mintable(rule!(require(minter.resource_address())), LOCKED)
+                        .updateable_non_fungible_data(rule!(require(minter.resource_address())), LOCKED)
+                        .no_initial_supply();
+
+            let component = Self {
+                minter: Vault::with_bucket(minter),
+                stake_pool: Vault::new(coal),
+                position_def: position_def,
+                nb_positions: 0,
+                tiers: tiers,
+                early_exit_penalty: dec!("0.1"),
+                total_weight: Decimal::zero(),
+                initial_emission: initial_emission,
+                halving_interval: halving_interval,
+                start_epoch: Runtime::current_epoch(),
+                max_supply: max_supply,
+                total_emitted: Decimal::zero(),
+                faucet_badge_def: faucet_badge_def,
+                nb_faucet_users: 0,
+                faucet_enabled: true,
+                reward_per_token: Decimal::zero(),
+                last_update_epoch: Runtime::current_epoch()
+            }.instantiate();
+
+            let access_rules = AccessRules::new()
+                .method("set_tier", rule!(require(admin_badge.resource_address())))
+                .method("set_early_exit_penalty", rule!(require(admin_badge.resource_address())))
+                .method("set_faucet_enabled", rule!(require(admin_badge.resource_address())))
+                .method("get_faucet_badge", rule!(require(admin_badge.resource_address())))
+                .default(rule!(allow_all));
+
+            (component.add_access_check(access_rules).globalize(), admin_badge)
+        }
+
+        // Allow caller to stake their coal tokens for the lock period of the chosen tier.
+        // This method sends a position NFT allowing the user to withdraw their funds later
+        pub fn stake(&mut self, coal: Bucket, tier: u8) -> Bucket {
+            assert!(coal.resource_address() == self.stake_pool.resource_address(), "You can only stake coal !");
+            let lock_tier = match self.tiers.get(&tier) {
+                Some(lock_tier) => lock_tier.clone(),
+                None => {
+                    info!("This lock tier does not exist !");
+                    std::process::abort();
+                }
+            };
+
+            self.update_reward_per_token();
+
+            // Save the stake's data on a new position NFT
+            let position = self.mint_position(StakerData {
+                started_at: Runtime::current_epoch(),
+                amount: coal.amount(),
+                lock_until: Runtime::current_epoch() + lock_tier.lock_epochs,
+                multiplier: lock_tier.multiplier,
+                reward_debt: self.reward_per_token,
+                pending_rewards: Decimal::zero()
+            });
+            self.total_weight += coal.amount() * lock_tier.multiplier;
+            self.stake_pool.put(coal);
+
+            // Return the position to the caller
+            position
+        }
+
+        // Add more coal to an existing stake
+        pub fn add_to_stake(&mut self, coal: Bucket, position: Proof) {
+            assert!(coal.resource_address() == self.stake_pool.resource_address(), "You can only stake coal !");
+
+            let position_id = self.position_id(position);
+            let mut staker_data = self.settled_position(&position_id);
+            staker_data.amount += coal.amount();
+            self.total_weight += coal.amount() * staker_data.multiplier;
+
+            self.stake_pool.put(coal);
+            self.save_position(&position_id, staker_data);
+        }
+
+        // Send the rewards earned so far without unstaking
+        pub fn claim_rewards(&mut self, position: Proof) -> Bucket {
+            let position_id = self.position_id(position);
+            let mut staker_data = self.settled_position(&position_id);
+            let rewards = staker_data.pending_rewards;
+            staker_data.pending_rewards = Decimal::zero();
+
+            self.save_position(&position_id, staker_data);
+            self.mint_coal(rewards)
+        }
+
+        // Withdraw part of the staked tokens.
+        // The rewards earned so far stay available to claim.
+        // A penalty is burned if the lock did not expire yet.
+        pub fn unstake(&mut self, amount: Decimal, position: Proof) -> Bucket {
+            let position_id = self.position_id(position);
+            let mut staker_data = self.settled_position(&position_id);
+            assert!(amount <= staker_data.amount, "You can't unstake more than you staked !");
+            staker_data.amount -= amount;
+            self.total_weight -= amount * staker_data.multiplier;
+
+            let lock_until = staker_data.lock_until;
+            self.save_position(&position_id, staker_data);
+            self.take_from_pool(amount, lock_until)
+        }
+
+        // Withdraw the staked tokens and rewards received.
+        pub fn withdraw(&mut self, position: Bucket) -> (Bucket, Bucket) {
+            assert!(position.resource_address() == self.position_def, "Wrong position NFT !");
+            assert!(position.amount() == Decimal::one(), "You can only withdraw one position at a time !");
+
+            let position_id = position.non_fungible::<StakerData>().id();
+            let staker_data = self.settled_position(&position_id);
+            self.total_weight -= staker_data.amount * staker_data.multiplier;
+
+            // Burn the position so that it cannot be used again
+            self.minter.authorize(|| {
+                position.burn()
+            });
+
+            // Mint the rewards earned by this stake
+            let reward = self.mint_coal(staker_data.pending_rewards);
+
+            // Return the staked amount + newly minted tokens
+            (self.take_from_pool(staker_data.amount, staker_data.lock_until), reward)
+        }
+
+        // Merge multiple positions of the same tier into a single one.
+        // The first position of the bucket is kept, the others are burned.
+        // The merged position stays locked until the latest lock expires.
+        pub fn merge_positions(&mut self, positions: Bucket) -> Bucket {
+            assert!(positions.resource_address() == self.position_def, "Wrong position NFT !");
+
+            let mut ids: Vec<NonFungibleId> = positions.non_fungible_ids().into_iter().collect();
+            assert!(ids.len() >= 2, "You need at least two positions to merge !");
+
+            let kept_id = ids.remove(0);
+            let mut kept_data = self.settled_position(&kept_id);
+
+            for id in ids.iter() {
+                let data = self.settled_position(id);
+                assert!(data.multiplier == kept_data.multiplier, "You can only merge positions of the same tier !");
+                kept_data.amount += data.amount;
+                kept_data.lock_until = kept_data.lock_until.max(data.lock_until);
+                kept_data.pending_rewards += data.pending_rewards;
+            }
+
+            // Burn the merged positions and update the one we keep
+            let mut positions = positions;
+            let kept = positions.take_non_fungible(&kept_id);
+            self.minter.authorize(|| {
+                positions.burn()
+            });
+            self.save_position(&kept_id, kept_data);
+
+            kept
+        }
+
+        // Move part of the stake of a position to a new position
+        pub fn split_position(&mut self, amount: Decimal, position: Proof) -> Bucket {
+            let position_id = self.position_id(position);
+            let mut staker_data = self.settled_position(&position_id);
+            assert!(amount > Decimal::zero() && amount < staker_data.amount, "Invalid amount to split !");
+            staker_data.amount -= amount;
+
+            let started_at = staker_data.started_at;
+            let lock_until = staker_data.lock_until;
+            let multiplier = staker_data.multiplier;
+            self.save_position(&position_id, staker_data);
+
+            // The new position keeps the same lock but does not carry any pending rewards
+            self.mint_position(StakerData {
+                started_at: started_at,
+                amount: amount,
+                lock_until: lock_until,
+                multiplier: multiplier,
+                reward_debt: self.reward_per_token,
+                pending_rewards: Decimal::zero()
+            })
+        }
+
+        // Create or update a lock tier
+        pub fn set_tier(&mut self, tier: u8, lock_epochs: u64, multiplier: Decimal) {
+            assert!(multiplier > Decimal::zero(), "The multiplier must be positive !");
+            self.tiers.insert(tier, LockTier { lock_epochs: lock_epochs, multiplier: multiplier });
+        }
+
+        // Set the fraction of the stake burned when exiting before the lock expires
+        pub fn set_early_exit_penalty(&mut self, penalty: Decimal) {
+            assert!(penalty >= Decimal::zero() && penalty <= Decimal::one(), "The penalty must be between 0 and 1 !");
+            self.early_exit_penalty = penalty;
+        }
+
+        // Return the available lock tiers
+        pub fn get_tiers(&self) -> HashMap<u8, LockTier> {
+            self.tiers.clone()
+        }
+
+        // Will be used by other components to
+        // get the coal resource definition
+        pub fn get_coal_resource(&self) -> ResourceAddress {
+            self.stake_pool.resource_address()
+        }
+
+        // Will be used by other components to
+        // get the staking position NFT resource definition
+        pub fn get_position_def(&self) -> ResourceAddress {
+            self.position_def
+        }
+
+        // Enable or disable the faucet
+        pub fn set_faucet_enabled(&mut self, enabled: bool) {
+            self.faucet_enabled = enabled;
+        }
+
+        // Return the current emission rate and the amount of coal
+        // that can still be emitted before reaching the supply cap
+        pub fn emission_info(&self) -> (Decimal, Decimal) {
+            let remaining_supply = self.max_supply - self.total_emitted;
+            let period = (Runtime::current_epoch() - self.start_epoch) / self.halving_interval;
+
+            let current_rate = if remaining_supply > Decimal::zero() {
+                self.emission_for_period(period)
+            } else {
+                Decimal::zero()
+            };
+
+            (current_rate, remaining_supply)
+        }
+
+        // As admin, send a badge allowing a tester to use the faucet.
+        // Restricted so that nobody can mint many badges to bypass the rate limit.
+        pub fn get_faucet_badge(&mut self) -> Bucket {
+            self.nb_faucet_users += 1;
+
+            self.minter.authorize(|| {
+                borrow_resource_manager!(self.faucet_badge_def)
+                    .mint_non_fungible(&NonFungibleId::from_u64(self.nb_faucet_users), FaucetUserData {
+                        last_claim_epoch: None
+                    })
+            })
+        }
+
+        // Send 1000 Coal tokens to the caller
+        // to help you test this component.
+        // Each faucet badge can only be used once per epoch.
+        pub fn faucet(&mut self, faucet_badge: Proof) -> Bucket {
+            assert!(self.faucet_enabled, "The faucet is disabled !");
+            assert!(faucet_badge.resource_address() == self.faucet_badge_def, "Wrong faucet badge !");
+
+            let faucet_user = faucet_badge.non_fungible::<FaucetUserData>();
+            let mut faucet_user_data = faucet_user.data();
+            faucet_badge.drop();
+
+            let current_epoch = Runtime::current_epoch();
+            assert!(faucet_user_data.last_claim_epoch != Some(current_epoch), "You already used the faucet this epoch, come back later !");
+            faucet_user_data.last_claim_epoch = Some(current_epoch);
+
+            self.minter.authorize(|| {
+                borrow_resource_manager!(self.faucet_badge_def).update_non_fungible_data(&faucet_user.id(), faucet_user_data)
+            });
+
+            // Update the rewards first so that the coal already
+            // emitted to the stakers is accounted for
+            self.update_reward_per_token();
+
+            let amount = dec!("1000");
+            assert!(self.total_emitted + amount <= self.max_supply, "The coal supply cap is reached !");
+            self.total_emitted += amount;
+
+            self.mint_coal(amount)
+        }
+
+        // Distribute the coal emitted since the last update
+        // between all the staked tokens, weighted by their tier multiplier
+        fn update_reward_per_token(&mut self) {
+            let current_epoch = Runtime::current_epoch();
+
+            if self.total_weight > Decimal::zero() && current_epoch > self.last_update_epoch {
+                // Never emit more than the supply cap
+                let remaining_supply = self.max_supply - self.total_emitted;
+                let mut emitted = self.emission_between(self.last_update_epoch, current_epoch);
+                if emitted > remaining_supply {
+                    emitted = remaining_supply;
+                }
+
+                self.total_emitted += emitted;
+                self.reward_per_token += emitted / self.total_weight;
+            }
+
+            self.last_update_epoch = current_epoch;
+        }
+
+        // Compute the amount of coal emitted between two epochs,
+        // taking the halvings happening in between into account
+        fn emission_between(&self, from: u64, to: u64) -> Decimal {
+            let mut emitted = Decimal::zero();
+            let mut epoch = from;
+
+            while epoch < to {
+                let period = (epoch - self.start_epoch) / self.halving_interval;
+                let period_end = self.start_epoch + (period + 1) * self.halving_interval;
+                let end = period_end.min(to);
+
+                emitted += self.emission_for_period(period) * Decimal::from(end - epoch);
+                epoch = end;
+            }
+
+            emitted
+        }
+
+        // Emission rate after the specified number of halvings
+        fn emission_for_period(&self, period: u64) -> Decimal {
+            // The rate is negligible after that many halvings
+            if period >= 64 {
+                return Decimal::zero();
+            }
+
+            let mut rate = self.initial_emission;
+            for _ in 0..period {
+                rate = rate / dec!("2");
+            }
+            rate
+        }
+
+        // Return the data of a position with the rewards earned
+        // since its last update moved to its pending rewards.
+        // The data still needs to be saved on the NFT.
+        fn settled_position(&mut self, position_id: &NonFungibleId) -> StakerData {
+            self.update_reward_per_token();
+
+            let mut staker_data: StakerData = borrow_resource_manager!(self.position_def).get_non_fungible_data(position_id);
+            staker_data.pending_rewards += staker_data.amount * staker_data.multiplier * (self.reward_per_token - staker_data.reward_debt);
+            staker_data.reward_debt = self.reward_per_token;
+            staker_data
+        }
+
+        // Make sure the proof is a position NFT and return its id
+        fn position_id(&self, position: Proof) -> NonFungibleId {
+            assert!(position.resource_address() == self.position_def, "Wrong position NFT !");
+            let position_id = position.non_fungible::<StakerData>().id();
+            position.drop();
+            position_id
+        }
+
+        fn mint_position(&mut self, staker_data: StakerData) -> Bucket {
+            self.nb_positions += 1;
+
+            self.minter.authorize(|| {
+                borrow_resource_manager!(self.position_def)
+                    .mint_non_fungible(&NonFungibleId::from_u64(self.nb_positions), staker_data)
+            })
+        }
+
+        fn save_position(&self, position_id: &NonFungibleId, staker_data: StakerData) {
+            self.minter.authorize(|| {
+                borrow_resource_manager!(self.position_def).update_non_fungible_data(position_id, staker_data)
+            });
+        }
+
+        // Take coal from the stake pool.
+        // If the lock did not expire yet, the penalty is burned.
+        fn take_from_pool(&mut self, amount: Decimal, lock_until: u64) -> Bucket {
+            let mut coal = self.stake_pool.take(amount);
+
+            if Runtime::current_epoch() < lock_until {
+                let penalty = coal.take(amount * self.early_exit_penalty);
+                info!("Your stake is still locked, {} coal were burned", penalty.amount());
+                self.minter.authorize(|| {
+                    penalty.burn()
+                });
+            }
+
+            coal
+        }
+
+        fn mint_coal(&self, amount: Decimal) -> Bucket {
+            self.minter.authorize(|| {
+                borrow_resource_manager!(self.stake_pool.resource_address()).mint(amount)
+            })
+        }
+    }
+}
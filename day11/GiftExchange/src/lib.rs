@@ -8,11 +8,33 @@ import! {
       "functions": [
         {
           "name": "new",
-          "inputs": [],
+          "inputs": [
+            {
+              "type": "U32"
+            },
+            {
+              "type": "U64"
+            },
+            {
+              "type": "Custom",
+              "name": "Decimal",
+              "generics": []
+            }
+          ],
           "output": {
-            "type": "Custom",
-            "name": "ComponentAddress",
-            "generics": []
+            "type": "Tuple",
+            "elements": [
+              {
+                "type": "Custom",
+                "name": "ComponentAddress",
+                "generics": []
+              },
+              {
+                "type": "Custom",
+                "name": "Bucket",
+                "generics": []
+              }
+            ]
           }
         }
       ],
@@ -41,6 +63,112 @@ import! {
             }
           }
         },
+        {
+          "name": "get_price_with_age",
+          "mutability": "Immutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Option",
+            "value": {
+              "type": "Tuple",
+              "elements": [
+                {
+                  "type": "Custom",
+                  "name": "Decimal",
+                  "generics": []
+                },
+                {
+                  "type": "U64"
+                }
+              ]
+            }
+          }
+        },
+        {
+          "name": "get_price_fresh",
+          "mutability": "Immutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "U64"
+            }
+          ],
+          "output": {
+            "type": "Custom",
+            "name": "Decimal",
+            "generics": []
+          }
+        },
+        {
+          "name": "get_twap",
+          "mutability": "Immutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "U64"
+            }
+          ],
+          "output": {
+            "type": "Option",
+            "value": {
+              "type": "Custom",
+              "name": "Decimal",
+              "generics": []
+            }
+          }
+        },
+        {
+          "name": "list_pairs",
+          "mutability": "Immutable",
+          "inputs": [],
+          "output": {
+            "type": "Vec",
+            "element": {
+              "type": "Tuple",
+              "elements": [
+                {
+                  "type": "Custom",
+                  "name": "ResourceAddress",
+                  "generics": []
+                },
+                {
+                  "type": "Custom",
+                  "name": "ResourceAddress",
+                  "generics": []
+                }
+              ]
+            }
+          }
+        },
         {
           "name": "get_usd_address",
           "mutability": "Immutable",
@@ -53,7 +181,7 @@ import! {
         },
         {
           "name": "update_price",
-          "mutability": "Immutable",
+          "mutability": "Mutable",
           "inputs": [
             {
               "type": "Custom",
@@ -69,6 +197,91 @@ import! {
               "type": "Custom",
               "name": "Decimal",
               "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "Proof",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
+        {
+          "name": "add_reporter",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "String"
+            }
+          ],
+          "output": {
+            "type": "Custom",
+            "name": "Bucket",
+            "generics": []
+          }
+        },
+        {
+          "name": "remove_reporter",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "NonFungibleId",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
+        {
+          "name": "set_min_reporters",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "U32"
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
+        {
+          "name": "set_submission_window",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "U64"
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
+        {
+          "name": "set_max_deviation",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "Decimal",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
+        {
+          "name": "set_pivot",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
             }
           ],
           "output": {
@@ -80,109 +293,247 @@ import! {
     "#
     }
 
+// Maximum number of participants tried while looking for a valid cycle,
+// so that impossible exclusions fail with a clear error instead of running out of cost units
+const MAX_CYCLE_ATTEMPTS: u32 = 10000;
+
+// Data stored on the participant badges
+#[derive(NonFungibleData)]
+pub struct ParticipantData {
+    // Account of the participant
+    account: ComponentAddress
+}
+
 blueprint! {
     struct GiftExchange {
       // Will store the price oracle component
       price_oracle: ComponentAddress,
+      // Badge allowing the component to mint participant badges
+      participant_minter: Vault,
+      // Resource definition of the participant badges
+      participant_def: ResourceAddress,
       // Keep track of the participants
-      participants: Vec<ResourceAddress>,
-      // Keep track of who should give to who
-      who_to_who: HashMap<ResourceAddress, ResourceAddress>,
+      participants: Vec<NonFungibleId>,
+      // Keep track of who should give to who.
+      // Participants can only query their own recipient.
+      who_to_who: HashMap<NonFungibleId, NonFungibleId>,
       // Indicates if the component decided who is going to give to who
       decided: bool,
       // Used to protect methods on this blueprint
       organizer_def: ResourceAddress,
+      // Maximum age, in epochs, of the prices used to check the gifts
+      max_price_age: u64,
+      // Pairs of participants that must not give to each other
+      exclusions: Vec<(NonFungibleId, NonFungibleId)>,
+      // Resource in which the budget is expressed
+      budget_quote: ResourceAddress,
+      // Minimum and maximum value of the gifts
+      min_budget: Decimal,
+      max_budget: Decimal,
+      // Maps recipients to the vault containing the gift they will receive.
+      // Gifts can be claimed once everyone sent theirs or the deadline passed.
+      escrow: HashMap<NonFungibleId, Vault>,
+      // Epoch after which the gifts can be released even if some participants did not send
+      send_deadline: u64,
+      // Indicates if the gifts were released to the recipients
+      released: bool,
+      // Funds used to compensate the participants whose giver did not send a gift
+      fallback_pool: Vault,
+      // Accounts of the participants who did not send their gift in time
+      defaulters: Vec<ComponentAddress>,
     }
 
     impl GiftExchange {
-        pub fn new(price_oracle_address: ComponentAddress) -> (ComponentAddress, Bucket) {
+        pub fn new(price_oracle_address: ComponentAddress, max_price_age: u64, budget_quote: ResourceAddress, min_budget: Decimal, max_budget: Decimal, fallback_resource: ResourceAddress) -> (ComponentAddress, Bucket) {
+            assert!(min_budget <= max_budget, "The minimum budget must be lower than the maximum budget !");
+
             // Create the organizer badge.
-            // Used to protect the `add_participant` and `prepare_exchange` methods
+            // Used to protect the `add_participant`, `add_exclusion` and `prepare_exchange` methods
             let organizer_badge = ResourceBuilder::new_fungible()
                                     .divisibility(DIVISIBILITY_NONE)
                                     .metadata("name", "Organizer Badge")
                                     .initial_supply(1);
 
+            let participant_minter = ResourceBuilder::new_fungible()
+                                    .divisibility(DIVISIBILITY_NONE)
+                                    .initial_supply(1);
+
+            // Define the participant badges, used as
+            // identification in the `send_gift` and `claim_gift` methods
+            let participant_def = ResourceBuilder::new_non_fungible()
+                                    .metadata("name", "Participant Badge")
+                                    .mintable(rule!(require(participant_minter.resource_address())), LOCKED)
+                                    .no_initial_supply();
+
             let component = Self {
                 price_oracle: price_oracle_address,
+                participant_minter: Vault::with_bucket(participant_minter),
+                participant_def: participant_def,
                 participants: Vec::new(),
                 who_to_who: HashMap::new(),
                 decided: false,
                 organizer_def: organizer_badge.resource_address(),
+                max_price_age: max_price_age,
+                exclusions: Vec::new(),
+                budget_quote: budget_quote,
+                min_budget: min_budget,
+                max_budget: max_budget,
+                escrow: HashMap::new(),
+                send_deadline: 0,
+                released: false,
+                fallback_pool: Vault::new(fallback_resource),
+                defaulters: Vec::new(),
             }
             .instantiate();
 
             let auth_rules = AccessRules::new()
               .method("add_participant", rule!(require(organizer_badge.resource_address())))
+              .method("add_exclusion", rule!(require(organizer_badge.resource_address())))
               .method("prepare_exchange", rule!(require(organizer_badge.resource_address())))
-              .method("send_gift", rule!(allow_all));
+              .method("fund_fallback", rule!(require(organizer_badge.resource_address())))
+              .method("withdraw_fallback", rule!(require(organizer_badge.resource_address())))
+              .method("send_gift", rule!(allow_all))
+              .method("get_my_recipient", rule!(allow_all))
+              .method("release_gifts", rule!(allow_all))
+              .method("claim_gift", rule!(allow_all))
+              .method("get_defaulters", rule!(allow_all));
 
             // Return the instantiated component and organizer's badge
             (component.add_access_check(auth_rules).globalize(), organizer_badge)
         }
 
-        // As organizer, add a participant to the gift exchange
-        pub fn add_participant(&mut self, address: ComponentAddress) {
+        // As organizer, add a participant to the gift exchange.
+        // Returns the participant's badge, which the organizer hands to the participant.
+        pub fn add_participant(&mut self, address: ComponentAddress) -> Bucket {
             assert!(!self.decided, "Component already decided who would give presents to who !");
 
-            // Create the participant's badge, used
-            // as identification in `send_gift` method
-            let participant_badge =  ResourceBuilder::new_fungible()
-                                        .divisibility(DIVISIBILITY_NONE)
-                                        .metadata("name", "Participant Badge")
-                                        .metadata("account", format!("{}", address))
-                                        .initial_supply(1);
+            let participant_id = NonFungibleId::from_u64(self.participants.len() as u64 + 1);
+            self.participants.push(participant_id.clone());
+
+            // Mint the participant's badge, holding their account address
+            let participant_def = self.participant_def;
+            self.participant_minter.authorize(|| {
+                borrow_resource_manager!(participant_def)
+                    .mint_non_fungible(&participant_id, ParticipantData { account: address })
+            })
+        }
 
-            self.participants.push(participant_badge.resource_address());
+        // As organizer, prevent two participants from giving to each other.
+        // Useful for spouses and siblings.
+        pub fn add_exclusion(&mut self, participant_a: NonFungibleId, participant_b: NonFungibleId) {
+            assert!(!self.decided, "Component already decided who would give presents to who !");
+            assert!(self.participants.contains(&participant_a) && self.participants.contains(&participant_b), "Both badges must belong to participants !");
+            assert!(participant_a != participant_b, "Can't exclude a participant with themselves !");
 
-            // Send the badge to the participant
-            borrow_component!(address).call::<()>("deposit", vec![scrypto_encode(&participant_badge)]);
+            self.exclusions.push((participant_a, participant_b));
         }
 
         // Organizer can call this method after adding the participants
         // to decide who should give to who.
-        pub fn prepare_exchange(&mut self) {
-            assert!(self.participants.len() >= 2, "Add at least two participants first !");
-            assert!(self.participants.len() % 2 == 0, "Need to have even number of participants !");
+        // The participants are shuffled and each one gives to the next one,
+        // forming a single cycle: nobody gives to the person giving to them.
+        // Gifts must be sent before the `send_deadline` epoch.
+        pub fn prepare_exchange(&mut self, send_deadline: u64) {
+            assert!(self.participants.len() >= 3, "Add at least three participants first !");
             assert!(!self.decided, "Component already decided who would give presents to who !");
+            assert!(send_deadline > Runtime::current_epoch(), "The deadline must be in the future !");
 
-            let amount_to_slice = self.participants.len() / 2;
+            // Everyone needs someone to give to and someone to receive from
+            for participant in self.participants.iter() {
+                let nb_partners = self.participants.iter()
+                    .filter(|other| *other != participant && self.can_give(participant, other))
+                    .count();
+                if nb_partners < 2 {
+                    info!("Impossible to find an assignment respecting the exclusions !");
+                    std::process::abort();
+                }
+            }
 
-            for i in 0..amount_to_slice {
-                let from = self.participants.get(i).unwrap();
-                let to = self.participants.get(i + amount_to_slice).unwrap();
-                
-                info!("{} is giving to {}", from, to);
-                info!("{} is giving to {}", to, from);
+            // Shuffle the participants
+            let mut remaining = self.participants.clone();
+            random::shuffle(&mut Runtime::generate_uuid, &mut remaining);
 
-                self.who_to_who.insert(*from, *to);
-                self.who_to_who.insert(*to, *from);
+            // Look for a cycle that honours the exclusions
+            let mut order = vec![remaining.remove(0)];
+            let mut attempts: u32 = 0;
+            if !self.extend_cycle(&mut order, &mut remaining, &mut attempts) {
+                info!("Impossible to find an assignment respecting the exclusions !");
+                std::process::abort();
+            }
+
+            for i in 0..order.len() {
+                let from = order[i].clone();
+                let to = order[(i + 1) % order.len()].clone();
+                self.who_to_who.insert(from, to);
             }
 
             // Set to true so that no one can call `make_exchange` and `add_participant` anymore
             self.decided = true;
+            self.send_deadline = send_deadline;
+        }
+
+        // As organizer, add funds used to compensate the participants
+        // whose giver did not send a gift
+        pub fn fund_fallback(&mut self, funds: Bucket) {
+            self.fallback_pool.put(funds);
+        }
+
+        // As organizer, take back what is left in the fallback pool
+        // once the forgotten participants were compensated
+        pub fn withdraw_fallback(&mut self) -> Bucket {
+            assert!(self.released, "The gifts are not released yet !");
+            self.fallback_pool.take_all()
+        }
+
+        // Allow participants to know who they should give to.
+        // Returns the account address of the recipient.
+        pub fn get_my_recipient(&self, your_badge: Proof) -> ComponentAddress {
+            assert!(self.decided, "The organizer did not prepare the exchange yet !");
+            let your_id = self.participant_id(your_badge);
+
+            let to_id = match self.who_to_who.get(&your_id) {
+                Some(to_id) => to_id,
+                None => {
+                    info!("Invalid badge");
+                    std::process::abort();
+                }
+            };
+
+            self.account_of(to_id)
         }
 
         // Allow participants to send their gift.
         // They only have to provide their badge. The destination is
         // fetched from the `who_to_who` map.
-        pub fn send_gift(&self, gift: Bucket, your_badge: Proof) {
-            assert!(self.participants.contains(&your_badge.resource_address()), "Invalid badge");
+        pub fn send_gift(&mut self, gift: Bucket, your_badge: Proof) {
+            let your_id = self.participant_id(your_badge);
             assert!(self.decided, "You have to call `make_exchange` first to decide who should give to who.");
-            assert!(self.who_to_who.contains_key(&your_badge.resource_address()), "Captain. What should we do? He's not on the list");
+            assert!(self.who_to_who.contains_key(&your_id), "Captain. What should we do? He's not on the list");
+            assert!(!self.released && Runtime::current_epoch() <= self.send_deadline, "Too late, the gifts were already given !");
 
-            let to_resource = borrow_resource_manager!(*self.who_to_who.get(&your_badge.resource_address()).unwrap());
-            your_badge.drop();
+            let to_id = self.who_to_who.get(&your_id).unwrap().clone();
+            assert!(!self.escrow.contains_key(&to_id), "You already sent your gift !");
 
             let oracle: PriceOracle = self.price_oracle.into();
 
-            // Make sure the provided gift price is less than 20$
-            match oracle.get_price(gift.resource_address(), oracle.get_usd_address()) {
-                Some(price) => {
-                    if price > dec!("20") {
+            // Make sure the provided gift price is recent and within the budget
+            match oracle.get_price_with_age(gift.resource_address(), self.budget_quote) {
+                Some((price, age)) => {
+                    if age > self.max_price_age {
+                        info!("The price of the gift is outdated, ask the oracle to update it !");
+                        std::process::abort();
+                    }
+
+                    let value = price * gift.amount();
+                    if value > self.max_budget {
                         info!("Gift is too expensive for the exchange ! Consider creating a YankeeSwap component instead");
                         std::process::abort();
                     }
+
+                    if value < self.min_budget {
+                        info!("Gift is too cheap for the exchange ! The minimum budget is {}", self.min_budget);
+                        std::process::abort();
+                    }
                 },
                 None => {
                     info!("Price of {} unknown", borrow_resource_manager!(gift.resource_address()).metadata().get("name").unwrap());
@@ -190,11 +541,119 @@ blueprint! {
                 }
             };
 
-            // Fetch the address from the metadata
-            let to_address: ComponentAddress = ComponentAddress::from_str(to_resource.metadata().get("account").unwrap()).unwrap();
+            // Hold the gift in the recipient's claim vault until everyone sent theirs
+            self.escrow.insert(to_id, Vault::with_bucket(gift));
+        }
+
+        // Release all the gifts at the same time, once every participant
+        // sent their gift or the deadline passed.
+        // Participants whose giver did not send anything get a share of the fallback pool.
+        pub fn release_gifts(&mut self) {
+            assert!(self.decided, "The organizer did not prepare the exchange yet !");
+            assert!(!self.released, "The gifts were already given !");
+            assert!(self.escrow.len() == self.participants.len() || Runtime::current_epoch() > self.send_deadline, "Wait for everyone to send their gift or for the deadline to pass !");
+
+            // Find the participants whose giver did not send a gift
+            let mut forgotten: Vec<NonFungibleId> = Vec::new();
+            for (from, to) in self.who_to_who.iter() {
+                if !self.escrow.contains_key(to) {
+                    let defaulter = self.account_of(from);
+                    forgotten.push(to.clone());
+                    self.defaulters.push(defaulter);
+                }
+            }
+
+            // Share the fallback pool between the forgotten participants,
+            // without exceeding the maximum budget when the oracle knows the value of the fallback resource
+            let mut compensation = Decimal::zero();
+            if !forgotten.is_empty() {
+                compensation = self.fallback_pool.amount() / Decimal::from(forgotten.len() as u64);
+
+                let oracle: PriceOracle = self.price_oracle.into();
+                if let Some(price) = oracle.get_price(self.fallback_pool.resource_address(), self.budget_quote) {
+                    let max_compensation = self.max_budget / price;
+                    if compensation > max_compensation {
+                        compensation = max_compensation;
+                    }
+                }
+            }
+            for to in forgotten {
+                self.escrow.insert(to, Vault::with_bucket(self.fallback_pool.take(compensation)));
+            }
+
+            self.released = true;
+        }
+
+        // Allow participants to withdraw their gift once the gifts are released
+        pub fn claim_gift(&mut self, your_badge: Proof) -> Bucket {
+            let your_id = self.participant_id(your_badge);
+            assert!(self.released, "The gifts are not released yet !");
+
+            match self.escrow.get_mut(&your_id) {
+                Some(gift) => gift.take_all(),
+                None => {
+                    info!("No gift for you !");
+                    std::process::abort();
+                }
+            }
+        }
+
+        // Returns the accounts of the participants who did not send their gift.
+        // Organizers of future exchanges can use it to decide who to invite.
+        pub fn get_defaulters(&self) -> Vec<ComponentAddress> {
+            self.defaulters.clone()
+        }
+
+        // Make sure the proof is a participant badge and return its id
+        fn participant_id(&self, badge: Proof) -> NonFungibleId {
+            assert!(badge.resource_address() == self.participant_def, "Invalid badge");
+            let id = badge.non_fungible::<ParticipantData>().id();
+            badge.drop();
+            id
+        }
+
+        // Fetch the account address stored on a participant badge
+        fn account_of(&self, participant_id: &NonFungibleId) -> ComponentAddress {
+            let data: ParticipantData = borrow_resource_manager!(self.participant_def).get_non_fungible_data(participant_id);
+            data.account
+        }
+
+        // Try to complete the cycle with the remaining participants, backtracking
+        // when a participant can't give to any of them.
+        // Returns false if no cycle respects the exclusions
+        // or if no cycle was found after `MAX_CYCLE_ATTEMPTS` tries.
+        fn extend_cycle(&self, order: &mut Vec<NonFungibleId>, remaining: &mut Vec<NonFungibleId>, attempts: &mut u32) -> bool {
+            let last = order.last().unwrap().clone();
+            if remaining.is_empty() {
+                // The last participant gives to the first one
+                return self.can_give(&last, &order[0]);
+            }
+
+            for i in 0..remaining.len() {
+                let candidate = remaining[i].clone();
+                if !self.can_give(&last, &candidate) {
+                    continue;
+                }
+
+                *attempts += 1;
+                if *attempts > MAX_CYCLE_ATTEMPTS {
+                    return false;
+                }
+
+                remaining.remove(i);
+                order.push(candidate.clone());
+                if self.extend_cycle(order, remaining, attempts) {
+                    return true;
+                }
+                order.pop();
+                remaining.insert(i, candidate);
+            }
+
+            false
+        }
 
-            // Deposit the gift into the recipient's account
-            borrow_component!(to_address).call::<()>("deposit", vec![scrypto_encode(&gift)]);
+        fn can_give(&self, from: &NonFungibleId, to: &NonFungibleId) -> bool {
+            !self.exclusions.iter().any(|(a, b)| (a == from && b == to) || (a == to && b == from))
         }
     }
 }
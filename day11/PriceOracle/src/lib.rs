@@ -1,32 +1,183 @@
 use scrypto::prelude::*;
 
+// Number of aggregated prices kept in the history of each pair
+const HISTORY_SIZE: usize = 64;
+
+// Data stored on the reporter badges
+#[derive(NonFungibleData)]
+pub struct ReporterData {
+    name: String
+}
+
 // PriceOrable component. Taken from https://github.com/radixdlt/radixdlt-scrypto examples.
-// I removed the admin badge for simplicity.
+// Prices are submitted by multiple reporters and the median
+// of the recent submissions is used as the price of a pair.
 blueprint! {
     struct PriceOracle {
-        /// Last price of each resource pair
-        prices: LazyMap<(ResourceAddress, ResourceAddress), Decimal>,
+        /// Prices submitted by each reporter for each resource pair, along with the submission epoch
+        submissions: LazyMap<(ResourceAddress, ResourceAddress), HashMap<NonFungibleId, (Decimal, u64)>>,
+        /// Ring buffer of the last aggregated prices of each resource pair, along with their epoch
+        history: LazyMap<(ResourceAddress, ResourceAddress), Vec<(Decimal, u64)>>,
+        /// Badge allowing the component to mint reporter badges
+        reporter_minter: Vault,
+        /// Resource definition of the reporter badges
+        reporter_def: ResourceAddress,
+        /// Reporters allowed to submit prices
+        reporters: HashSet<NonFungibleId>,
+        /// Number of reporter badges minted
+        nb_reporters: u64,
+        /// Minimum number of submissions required for a price to be valid
+        min_reporters: u32,
+        /// Number of epochs during which a submission is taken into account
+        submission_window: u64,
+        /// Maximum relative change allowed between a submission and the current price
+        max_deviation: Decimal,
+        /// Resource used to derive the prices of the pairs that are not submitted directly
+        pivot: ResourceAddress,
+        /// Pairs for which prices were submitted
+        pairs: Vec<(ResourceAddress, ResourceAddress)>,
         usd: Vault
     }
 
     impl PriceOracle {
         /// Creates a PriceOracle component, along with admin badges.
-        pub fn new() -> ComponentAddress {
+        pub fn new(min_reporters: u32, submission_window: u64, max_deviation: Decimal) -> (ComponentAddress, Bucket) {
+            assert!(min_reporters > 0, "At least one reporter is required !");
+            assert!(max_deviation > Decimal::zero(), "The maximum deviation must be positive !");
+
             // Create usd tokens
             let usd = ResourceBuilder::new_fungible()
                         .metadata("name", "USD")
                         .initial_supply(100000);
 
-            Self {
-                prices: LazyMap::new(),
+            // Create the admin badge, used to manage the reporters
+            let admin_badge = ResourceBuilder::new_fungible()
+                        .divisibility(DIVISIBILITY_NONE)
+                        .metadata("name", "PriceOracle Admin")
+                        .initial_supply(1);
+
+            let reporter_minter = ResourceBuilder::new_fungible()
+                        .divisibility(DIVISIBILITY_NONE)
+                        .initial_supply(1);
+
+            // Define the badge identifying the reporters
+            let reporter_def = ResourceBuilder::new_non_fungible()
+                        .metadata("name", "PriceOracle Reporter")
+                        .mintable(rule!(require(reporter_minter.resource_address())), LOCKED)
+                        .no_initial_supply();
+
+            let component = Self {
+                submissions: LazyMap::new(),
+                history: LazyMap::new(),
+                reporter_minter: Vault::with_bucket(reporter_minter),
+                reporter_def: reporter_def,
+                reporters: HashSet::new(),
+                nb_reporters: 0,
+                min_reporters: min_reporters,
+                submission_window: submission_window,
+                max_deviation: max_deviation,
+                pivot: usd.resource_address(),
+                pairs: Vec::new(),
                 usd: Vault::with_bucket(usd)
             }
-            .instantiate().globalize()
+            .instantiate();
+
+            let access_rules = AccessRules::new()
+                .method("add_reporter", rule!(require(admin_badge.resource_address())))
+                .method("remove_reporter", rule!(require(admin_badge.resource_address())))
+                .method("set_min_reporters", rule!(require(admin_badge.resource_address())))
+                .method("set_submission_window", rule!(require(admin_badge.resource_address())))
+                .method("set_max_deviation", rule!(require(admin_badge.resource_address())))
+                .method("set_pivot", rule!(require(admin_badge.resource_address())))
+                .default(rule!(allow_all));
+
+            (component.add_access_check(access_rules).globalize(), admin_badge)
         }
 
         /// Returns the current price of a resource pair BASE/QUOTE.
+        /// The price is the median of the recent submissions of the active reporters
+        /// and is only valid if enough reporters submitted a price.
+        /// Pairs that are not submitted directly are derived through the pivot resource.
         pub fn get_price(&self, base: ResourceAddress, quote: ResourceAddress) -> Option<Decimal> {
-            self.prices.get(&(base, quote))
+            self.resolve(base, quote).map(|(price, _)| price)
+        }
+
+        /// Returns the current price of a resource pair BASE/QUOTE along with
+        /// its age, in epochs since the last submission.
+        pub fn get_price_with_age(&self, base: ResourceAddress, quote: ResourceAddress) -> Option<(Decimal, u64)> {
+            self.resolve(base, quote).map(|(price, epoch)| (price, Runtime::current_epoch() - epoch))
+        }
+
+        /// Returns the current price of a resource pair BASE/QUOTE.
+        /// Fails if the price is unknown or older than `max_age` epochs.
+        pub fn get_price_fresh(&self, base: ResourceAddress, quote: ResourceAddress, max_age: u64) -> Decimal {
+            match self.get_price_with_age(base, quote) {
+                Some((price, age)) => {
+                    assert!(age <= max_age, "The price is stale !");
+                    price
+                },
+                None => {
+                    info!("Price not found !");
+                    std::process::abort();
+                }
+            }
+        }
+
+        /// Returns the time-weighted average price of a resource pair BASE/QUOTE
+        /// over the last `window` epochs. Only available for the pairs submitted directly.
+        pub fn get_twap(&self, base: ResourceAddress, quote: ResourceAddress, window: u64) -> Option<Decimal> {
+            let history = self.history.get(&(base, quote))?;
+            let (last_price, _) = *history.last()?;
+
+            let current_epoch = Runtime::current_epoch();
+            let start = if current_epoch > window { current_epoch - window } else { 0 };
+
+            // Each price is valid from its epoch until the epoch of the next one
+            let mut weighted_sum = Decimal::zero();
+            let mut total_epochs: u64 = 0;
+            for (i, (price, epoch)) in history.iter().enumerate() {
+                let until = match history.get(i + 1) {
+                    Some((_, next_epoch)) => *next_epoch,
+                    None => current_epoch
+                };
+                let from = if *epoch > start { *epoch } else { start };
+
+                if until > from {
+                    weighted_sum += *price * Decimal::from(until - from);
+                    total_epochs += until - from;
+                }
+            }
+
+            if total_epochs == 0 {
+                // All the prices were set during the current epoch
+                return Some(last_price);
+            }
+
+            Some(weighted_sum / Decimal::from(total_epochs))
+        }
+
+        /// Returns every pair with a valid price, including the ones derived through the pivot
+        pub fn list_pairs(&self) -> Vec<(ResourceAddress, ResourceAddress)> {
+            let mut pairs: Vec<(ResourceAddress, ResourceAddress)> = Vec::new();
+
+            for (base, quote) in self.pairs.iter() {
+                if self.resolve(*base, *quote).is_some() && !pairs.contains(&(*base, *quote)) {
+                    pairs.push((*base, *quote));
+                }
+
+                // Pairs priced against the pivot can be combined with each other
+                if *quote != self.pivot {
+                    continue;
+                }
+                for (pivot, other) in self.pairs.iter() {
+                    if *pivot == self.pivot && *other != *base
+                        && self.resolve(*base, *other).is_some() && !pairs.contains(&(*base, *other)) {
+                        pairs.push((*base, *other));
+                    }
+                }
+            }
+
+            pairs
         }
 
         // Return the address of USD token
@@ -34,10 +185,159 @@ blueprint! {
             self.usd.resource_address()
         }
 
-        /// Updates the price of a resource pair BASE/QUOTE and its inverse.
-        pub fn update_price(&self, base: ResourceAddress, quote: ResourceAddress, price: Decimal) {
-            self.prices.insert((base, quote), price);
-            self.prices.insert((quote, base), Decimal::one() / price);
+        /// Submits the price of a resource pair BASE/QUOTE and its inverse.
+        /// Only callable by the reporters.
+        pub fn update_price(&mut self, base: ResourceAddress, quote: ResourceAddress, price: Decimal, reporter: Proof) {
+            assert!(reporter.resource_address() == self.reporter_def, "Wrong reporter badge !");
+            let reporter_id = reporter.non_fungible::<ReporterData>().id();
+            reporter.drop();
+            assert!(self.reporters.contains(&reporter_id), "This reporter was removed !");
+            assert!(base != quote, "Can't set the price of a resource against itself !");
+            assert!(price > Decimal::zero(), "The price must be positive !");
+
+            // Reject the prices too far from the last recorded one.
+            // The history is used so that the check still applies once the submissions are stale.
+            if let Some((last_price, _)) = self.history.get(&(base, quote)).and_then(|history| history.last().cloned()) {
+                let deviation = if price > last_price { price - last_price } else { last_price - price };
+                assert!(deviation <= last_price * self.max_deviation, "The price deviates too much from the last recorded one !");
+            }
+
+            if !self.pairs.contains(&(base, quote)) {
+                self.pairs.push((base, quote));
+                self.pairs.push((quote, base));
+            }
+
+            let current_epoch = Runtime::current_epoch();
+
+            let mut submissions = self.submissions.get(&(base, quote)).unwrap_or(HashMap::new());
+            submissions.insert(reporter_id.clone(), (price, current_epoch));
+            self.submissions.insert((base, quote), submissions);
+
+            let mut inverse_submissions = self.submissions.get(&(quote, base)).unwrap_or(HashMap::new());
+            inverse_submissions.insert(reporter_id, (Decimal::one() / price, current_epoch));
+            self.submissions.insert((quote, base), inverse_submissions);
+
+            self.record_history(base, quote);
+            self.record_history(quote, base);
+        }
+
+        /// Mints a badge allowing its holder to submit prices
+        pub fn add_reporter(&mut self, name: String) -> Bucket {
+            self.nb_reporters += 1;
+            self.reporters.insert(NonFungibleId::from_u64(self.nb_reporters));
+
+            let nb_reporters = self.nb_reporters;
+            let reporter_def = self.reporter_def;
+            self.reporter_minter.authorize(|| {
+                borrow_resource_manager!(reporter_def)
+                    .mint_non_fungible(&NonFungibleId::from_u64(nb_reporters), ReporterData { name: name })
+            })
+        }
+
+        /// Prevents a reporter from submitting prices.
+        /// Its previous submissions are ignored.
+        pub fn remove_reporter(&mut self, reporter_id: NonFungibleId) {
+            assert!(self.reporters.remove(&reporter_id), "Reporter not found !");
+        }
+
+        /// Sets the minimum number of submissions required for a price to be valid
+        pub fn set_min_reporters(&mut self, min_reporters: u32) {
+            assert!(min_reporters > 0, "At least one reporter is required !");
+            self.min_reporters = min_reporters;
+        }
+
+        /// Sets the number of epochs during which a submission is taken into account
+        pub fn set_submission_window(&mut self, submission_window: u64) {
+            self.submission_window = submission_window;
+        }
+
+        /// Sets the maximum relative change allowed between a submission and the current price
+        pub fn set_max_deviation(&mut self, max_deviation: Decimal) {
+            assert!(max_deviation > Decimal::zero(), "The maximum deviation must be positive !");
+            self.max_deviation = max_deviation;
+        }
+
+        /// Sets the resource used to derive the prices of the pairs that are not submitted directly
+        pub fn set_pivot(&mut self, pivot: ResourceAddress) {
+            self.pivot = pivot;
+        }
+
+        /// Returns the price of a resource pair BASE/QUOTE, either submitted directly
+        /// or derived from BASE/PIVOT and PIVOT/QUOTE, along with the epoch of its last submission.
+        fn resolve(&self, base: ResourceAddress, quote: ResourceAddress) -> Option<(Decimal, u64)> {
+            if base == quote {
+                return Some((Decimal::one(), Runtime::current_epoch()));
+            }
+
+            if let Some(aggregated) = self.aggregate(base, quote) {
+                return Some(aggregated);
+            }
+
+            if base == self.pivot || quote == self.pivot {
+                return None;
+            }
+
+            let (base_price, base_epoch) = self.aggregate(base, self.pivot)?;
+            let (quote_price, quote_epoch) = self.aggregate(self.pivot, quote)?;
+
+            // The derived price is as old as the oldest of the two prices
+            Some((base_price * quote_price, base_epoch.min(quote_epoch)))
+        }
+
+        /// Returns the median of the recent submissions of the active reporters
+        /// for a resource pair BASE/QUOTE, along with the epoch of the last submission.
+        /// Returns None if not enough reporters submitted a price.
+        fn aggregate(&self, base: ResourceAddress, quote: ResourceAddress) -> Option<(Decimal, u64)> {
+            let submissions = self.submissions.get(&(base, quote))?;
+            let current_epoch = Runtime::current_epoch();
+
+            let fresh: Vec<(Decimal, u64)> = submissions.iter()
+                .filter(|(reporter, (_, epoch))| {
+                    self.reporters.contains(*reporter) && *epoch + self.submission_window >= current_epoch
+                })
+                .map(|(_, submission)| *submission)
+                .collect();
+
+            if fresh.len() < self.min_reporters as usize {
+                return None;
+            }
+
+            let last_epoch = fresh.iter().map(|(_, epoch)| *epoch).max()?;
+            let mut prices: Vec<Decimal> = fresh.into_iter().map(|(price, _)| price).collect();
+            prices.sort();
+
+            let middle = prices.len() / 2;
+            let median = if prices.len() % 2 == 0 {
+                (prices[middle - 1] + prices[middle]) / dec!("2")
+            } else {
+                prices[middle]
+            };
+
+            Some((median, last_epoch))
+        }
+
+        /// Adds the current aggregated price of a resource pair to its history.
+        /// Only the last price of each epoch is kept.
+        fn record_history(&self, base: ResourceAddress, quote: ResourceAddress) {
+            let (price, epoch) = match self.aggregate(base, quote) {
+                Some(aggregated) => aggregated,
+                None => return
+            };
+
+            let mut history = self.history.get(&(base, quote)).unwrap_or(Vec::new());
+            match history.last() {
+                Some((_, last_epoch)) if *last_epoch == epoch => {
+                    history.pop();
+                },
+                _ => {}
+            }
+
+            history.push((price, epoch));
+            if history.len() > HISTORY_SIZE {
+                history.remove(0);
+            }
+
+            self.history.insert((base, quote), history);
         }
     }
 }
@@ -0,0 +1,689 @@
+use scrypto::prelude::*;
+use sbor::*;
+
+import! {
+    r#"
+    {
+      "package_address": "01ecb27f6b7977c3b588bf275375c7ee43eb340e4f65481d1ee7b3",
+      "blueprint_name": "PriceOracle",
+      "functions": [
+        {
+          "name": "new",
+          "inputs": [
+            {
+              "type": "U32"
+            },
+            {
+              "type": "U64"
+            },
+            {
+              "type": "Custom",
+              "name": "Decimal",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Tuple",
+            "elements": [
+              {
+                "type": "Custom",
+                "name": "ComponentAddress",
+                "generics": []
+              },
+              {
+                "type": "Custom",
+                "name": "Bucket",
+                "generics": []
+              }
+            ]
+          }
+        }
+      ],
+      "methods": [
+        {
+          "name": "get_price",
+          "mutability": "Immutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Option",
+            "value": {
+              "type": "Custom",
+              "name": "Decimal",
+              "generics": []
+            }
+          }
+        },
+        {
+          "name": "get_price_with_age",
+          "mutability": "Immutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Option",
+            "value": {
+              "type": "Tuple",
+              "elements": [
+                {
+                  "type": "Custom",
+                  "name": "Decimal",
+                  "generics": []
+                },
+                {
+                  "type": "U64"
+                }
+              ]
+            }
+          }
+        },
+        {
+          "name": "get_price_fresh",
+          "mutability": "Immutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "U64"
+            }
+          ],
+          "output": {
+            "type": "Custom",
+            "name": "Decimal",
+            "generics": []
+          }
+        },
+        {
+          "name": "get_twap",
+          "mutability": "Immutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "U64"
+            }
+          ],
+          "output": {
+            "type": "Option",
+            "value": {
+              "type": "Custom",
+              "name": "Decimal",
+              "generics": []
+            }
+          }
+        },
+        {
+          "name": "list_pairs",
+          "mutability": "Immutable",
+          "inputs": [],
+          "output": {
+            "type": "Vec",
+            "element": {
+              "type": "Tuple",
+              "elements": [
+                {
+                  "type": "Custom",
+                  "name": "ResourceAddress",
+                  "generics": []
+                },
+                {
+                  "type": "Custom",
+                  "name": "ResourceAddress",
+                  "generics": []
+                }
+              ]
+            }
+          }
+        },
+        {
+          "name": "get_usd_address",
+          "mutability": "Immutable",
+          "inputs": [],
+          "output": {
+            "type": "Custom",
+            "name": "ResourceAddress",
+            "generics": []
+          }
+        },
+        {
+          "name": "update_price",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "Decimal",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "Proof",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
+        {
+          "name": "add_reporter",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "String"
+            }
+          ],
+          "output": {
+            "type": "Custom",
+            "name": "Bucket",
+            "generics": []
+          }
+        },
+        {
+          "name": "remove_reporter",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "NonFungibleId",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
+        {
+          "name": "set_min_reporters",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "U32"
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
+        {
+          "name": "set_submission_window",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "U64"
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
+        {
+          "name": "set_max_deviation",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "Decimal",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
+        {
+          "name": "set_pivot",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        }
+      ]
+    }
+    "#
+    }
+
+// Data stored on the participant tickets
+#[derive(NonFungibleData)]
+pub struct TicketData {
+    // Epoch at which the participant entered the game
+    entered_at: u64
+}
+
+// Phase of the game
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub enum Phase {
+    // Participants can enter the game
+    Registration,
+    // Participants are taking turns
+    Playing,
+    // Participants can withdraw their gift
+    Ended
+}
+
+// State of one of the gifts of the game
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe)]
+pub struct GiftState {
+    // Resource of the gift. Hidden until the gift is opened.
+    resource: Option<ResourceAddress>,
+    // Ticket of the participant holding the gift
+    holder: Option<NonFungibleId>,
+    // Number of times the gift was stolen
+    steals: u32,
+    // Whether the gift can't be stolen anymore
+    frozen: bool
+}
+
+// Everything a front-end needs to display the game
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe)]
+pub struct GameState {
+    phase: Phase,
+    // Ticket of the participant that should keep or steal a gift
+    current_player: Option<NonFungibleId>,
+    // Epoch after which anyone can make the current player keep their gift
+    turn_deadline: u64,
+    // Gifts, ordered by index
+    gifts: Vec<GiftState>
+}
+
+// Action taken by a participant during their turn
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub enum MoveKind {
+    // The participant kept their gift
+    Keep,
+    // The participant stole the gift at this index
+    Swap(u32),
+    // The participant did not play in time and kept their gift
+    TimedOut
+}
+
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe)]
+pub struct Move {
+    player: NonFungibleId,
+    kind: MoveKind,
+    epoch: u64
+}
+
+blueprint! {
+    struct YankeeSwap {
+        // Keep track of the different gifs
+        gifts: Vec<Vault>,
+        // Badge allowing the component to mint participant tickets
+        ticket_minter: Vault,
+        // Resource definition of the participant tickets
+        ticket_def: ResourceAddress,
+        // Keep track of the participant tickets, in playing order once the game started
+        participants: Vec<NonFungibleId>,
+        // Ticket of the participant holding each gift, indexed like `gifts`
+        gift_holders: Vec<Option<NonFungibleId>>,
+        // Definition of the admin badge, allowing us to secure some methods
+        admin_badge_def: ResourceAddress,
+        // Number of times each gift was stolen, indexed like `gifts`
+        steal_counts: Vec<u32>,
+        // Number of steals after which a gift is frozen and can't be stolen anymore
+        max_steals: u32,
+        // Whether a player can immediately steal back the gift that was just stolen from them
+        allow_steal_back: bool,
+        // Number of epochs a player has to keep or steal a gift
+        turn_duration: u64,
+        // Every keep or swap, in the order they were played
+        history: Vec<Move>,
+        // Maximum number of participants that can enter the game
+        max_participants: u32,
+        // Epoch after which nobody can enter the game anymore
+        entry_deadline: u64,
+        // Oracle, quote resource and minimum value of the gifts.
+        // The value of the gifts is not checked when not set.
+        min_gift_value: Option<(ComponentAddress, ResourceAddress, Decimal)>,
+
+        // Store the current state of the game
+        phase: Phase,
+        current_gift_index: usize,
+        // Participant that should keep or steal a gift
+        current_player: Option<NonFungibleId>,
+        // Gift that was just stolen from the current player
+        last_stolen: Option<u32>,
+        // Epoch after which anyone can make the current player keep their gift
+        turn_deadline: u64,
+    }
+
+    impl YankeeSwap {
+        // Instantiate a new game. Gifts are frozen after being stolen `max_steals` times.
+        // When `allow_steal_back` is false, a player can't immediately steal back the gift
+        // that was just stolen from them.
+        // Players have `turn_duration` epochs to play before their turn can be skipped.
+        // Up to `max_participants` can enter the game before the `entry_deadline` epoch.
+        pub fn new(max_steals: u32, allow_steal_back: bool, turn_duration: u64, max_participants: u32, entry_deadline: u64) -> (ComponentAddress, Bucket) {
+            assert!(max_participants >= 2, "At least two participants are required !");
+
+            // Create the admin badge
+            let admin_badge = ResourceBuilder::new_fungible()
+                                .divisibility(DIVISIBILITY_NONE)
+                                .metadata("name", "Admin")
+                                .initial_supply(1);
+
+            let ticket_minter = ResourceBuilder::new_fungible()
+                                .divisibility(DIVISIBILITY_NONE)
+                                .initial_supply(1);
+
+            // Define the tickets that will allow us to
+            // identify the users
+            let ticket_def = ResourceBuilder::new_non_fungible()
+                                .metadata("name", "YankeeSwap Ticket")
+                                .mintable(rule!(require(ticket_minter.resource_address())), LOCKED)
+                                .no_initial_supply();
+
+            let component = Self {
+                gifts: Vec::new(),
+                ticket_minter: Vault::with_bucket(ticket_minter),
+                ticket_def: ticket_def,
+                participants: Vec::new(),
+                gift_holders: Vec::new(),
+                admin_badge_def: admin_badge.resource_address(),
+                steal_counts: Vec::new(),
+                max_steals: max_steals,
+                allow_steal_back: allow_steal_back,
+                turn_duration: turn_duration,
+                history: Vec::new(),
+                max_participants: max_participants,
+                entry_deadline: entry_deadline,
+                min_gift_value: None,
+                phase: Phase::Registration,
+                current_gift_index: 0,
+                current_player: None,
+                last_stolen: None,
+                turn_deadline: 0
+            }
+            .instantiate();
+
+            let auth_rules = AccessRules::new()
+                .method("start", rule!(require(admin_badge.resource_address())))
+                .method("next_turn", rule!(require(admin_badge.resource_address())))
+                .method("set_min_gift_value", rule!(require(admin_badge.resource_address())))
+                .default(rule!(allow_all));
+
+            // Return the component and admin_badge to the caller
+            (component.add_access_check(auth_rules).globalize(), admin_badge)
+        }
+
+        // Return the current state of the game and the gifts.
+        // Gifts that are not opened yet stay hidden.
+        pub fn get_state(&self) -> GameState {
+            let mut gifts: Vec<GiftState> = Vec::new();
+            for (i, vault) in self.gifts.iter().enumerate() {
+                let opened = self.phase == Phase::Ended || (self.phase == Phase::Playing && i <= self.current_gift_index);
+                gifts.push(GiftState {
+                    resource: if opened { Some(vault.resource_address()) } else { None },
+                    holder: self.gift_holders[i].clone(),
+                    steals: self.steal_counts[i],
+                    frozen: self.steal_counts[i] >= self.max_steals
+                });
+            }
+
+            GameState {
+                phase: self.phase.clone(),
+                current_player: self.current_player.clone(),
+                turn_deadline: self.turn_deadline,
+                gifts: gifts
+            }
+        }
+
+        // Return every keep or swap played so far
+        pub fn history(&self) -> Vec<Move> {
+            self.history.clone()
+        }
+
+        // Indicates if participants can still enter the game
+        pub fn is_open(&self) -> bool {
+            self.phase == Phase::Registration
+                && Runtime::current_epoch() <= self.entry_deadline
+                && (self.participants.len() as u32) < self.max_participants
+        }
+
+        // As admin, require the gifts to be worth at least `min_value`,
+        // expressed in the `quote` resource and priced by the oracle
+        pub fn set_min_gift_value(&mut self, price_oracle: ComponentAddress, quote: ResourceAddress, min_value: Decimal) {
+            assert!(self.phase == Phase::Registration, "Game already started !");
+            self.min_gift_value = Some((price_oracle, quote, min_value));
+        }
+
+        // Allow anybody to enter the game.
+        // They must send a bucket containing the gift to contribute.
+        pub fn enter_swap(&mut self, gift: Bucket) -> Bucket {
+            // Make sure the game is not already started
+            assert!(self.phase == Phase::Registration, "Game already started !");
+            assert!(Runtime::current_epoch() <= self.entry_deadline, "Too late, the entries are closed !");
+            assert!((self.participants.len() as u32) < self.max_participants, "The game is full !");
+
+            // Make sure the gift is valuable enough
+            if let Some((price_oracle, quote, min_value)) = &self.min_gift_value {
+                let oracle: PriceOracle = (*price_oracle).into();
+                match oracle.get_price(gift.resource_address(), *quote) {
+                    Some(price) => {
+                        assert!(price * gift.amount() >= *min_value, "Your gift is not valuable enough ! The minimum value is {}", min_value);
+                    },
+                    None => {
+                        info!("Price of {} unknown", borrow_resource_manager!(gift.resource_address()).metadata().get("name").unwrap());
+                        std::process::abort();
+                    }
+                }
+            }
+
+            let ticket_id = NonFungibleId::from_u64(self.participants.len() as u64 + 1);
+
+            // Mint a new ticket that will allow us to
+            // identify the user
+            let ticket_def = self.ticket_def;
+            let ticket = self.ticket_minter.authorize(|| {
+                borrow_resource_manager!(ticket_def)
+                    .mint_non_fungible(&ticket_id, TicketData { entered_at: Runtime::current_epoch() })
+            });
+
+            self.gift_holders.push(None);
+            self.steal_counts.push(0);
+
+            // Store the gift in a new vault because vaults can only
+            // store one kind of token.
+            self.gifts.push(Vault::with_bucket(gift));
+
+            // Add the ticket to the list of participants
+            self.participants.push(ticket_id);
+
+            // Return the ticket to the caller
+            ticket
+        }
+
+        // Allow the current player to steal an opened gift at specified index.
+        // The player whose gift was stolen receives the current player's gift
+        // and takes the next turn.
+        pub fn swap(&mut self, with_index: u32, participant_badge: Proof) {
+            let player = self.check_turn(participant_badge);
+
+            assert!(with_index as usize <= self.current_gift_index, "Only opened gifts can be stolen !");
+            let victim = self.gift_holders[with_index as usize].clone().unwrap();
+            assert!(victim != player, "You already have this gift !");
+
+            let steals = self.steal_counts[with_index as usize];
+            assert!(steals < self.max_steals, "This gift is frozen, it can't be stolen anymore !");
+            assert!(self.allow_steal_back || self.last_stolen != Some(with_index), "You can't steal back the gift that was just stolen from you !");
+
+            // Swap the owner of the gifts
+            let player_gift = self.gift_of(&player);
+            self.gift_holders[player_gift] = Some(victim.clone());
+            self.gift_holders[with_index as usize] = Some(player.clone());
+            self.steal_counts[with_index as usize] = steals + 1;
+            self.record_move(player, MoveKind::Swap(with_index));
+
+            // The player whose gift was stolen takes the next turn
+            info!("Gift stolen ! {} can now keep their new gift or steal another one", victim);
+            self.current_player = Some(victim);
+            self.last_stolen = Some(with_index);
+            self.turn_deadline = Runtime::current_epoch() + self.turn_duration;
+        }
+
+        // Allow the current player to keep their gift
+        pub fn keep(&mut self, participant_badge: Proof) {
+            let player = self.check_turn(participant_badge);
+            self.record_move(player, MoveKind::Keep);
+
+            self.next_turn();
+        }
+
+        // Allow anybody to move the game on when the current player
+        // did not play before the deadline. The player keeps their gift.
+        pub fn advance_if_timed_out(&mut self) {
+            assert!(self.phase == Phase::Playing, "The game is not running !");
+            assert!(Runtime::current_epoch() > self.turn_deadline, "The current player still has time to play !");
+
+            let player = self.current_player.clone().unwrap();
+            info!("{} took too long, they keep their gift", player);
+            self.record_move(player, MoveKind::TimedOut);
+            self.next_turn();
+        }
+
+        // After the game is ended, participants can call
+        // this method to withdraw their gift.
+        // If the entries closed without enough participants to play,
+        // participants get back the gift they brought.
+        pub fn withdraw(&mut self, participant_badge: Proof) -> Bucket {
+            assert!(participant_badge.resource_address() == self.ticket_def, "Invalid badge provided");
+
+            let participant = participant_badge.non_fungible::<TicketData>().id();
+            participant_badge.drop();
+
+            if self.phase == Phase::Registration {
+                assert!(Runtime::current_epoch() > self.entry_deadline && self.participants.len() < 2, "Game not completed !");
+
+                // The participants are not shuffled yet, so they are in the same order as their gifts
+                let index = self.participants.iter().position(|id| *id == participant).unwrap();
+                return self.gifts[index].take_all();
+            }
+
+            assert!(self.phase == Phase::Ended, "Game not completed !");
+            let index = self.gift_of(&participant);
+            self.gifts[index].take_all()
+        }
+
+        // Call this method with the admin badge to
+        // start the game.
+        pub fn start(&mut self) {
+            assert!(self.phase == Phase::Registration, "Already started !");
+            assert!(self.participants.len() >= 2, "At least two participants are required !");
+
+            // Reorder the list of participants randomly
+            random::shuffle(&mut Runtime::generate_uuid, &mut self.participants);
+
+            self.phase = Phase::Playing;
+
+            self.gift_holders[0] = Some(self.participants[0].clone());
+            self.gift_holders[1] = Some(self.participants[1].clone());
+            self.current_gift_index += 1;
+            self.current_player = Some(self.participants[1].clone());
+            self.turn_deadline = Runtime::current_epoch() + self.turn_duration;
+        }
+
+        // Make sure it's the turn of the participant presenting the ticket
+        // and return the ticket's id
+        fn check_turn(&self, participant_badge: Proof) -> NonFungibleId {
+            assert!(participant_badge.resource_address() == self.ticket_def, "Invalid badge provided!");
+            let participant = participant_badge.non_fungible::<TicketData>().id();
+            participant_badge.drop();
+
+            assert!(self.current_player == Some(participant.clone()), "It's not your turn !");
+            participant
+        }
+
+        fn record_move(&mut self, player: NonFungibleId, kind: MoveKind) {
+            self.history.push(Move {
+                player: player,
+                kind: kind,
+                epoch: Runtime::current_epoch()
+            });
+        }
+
+        // Move to the next turn or end the game.
+        // The next participant opens the next gift.
+        fn next_turn(&mut self) {
+            self.last_stolen = None;
+            if self.current_gift_index < self.participants.len() - 1 {
+                info!("Next person's turn !");
+                self.current_gift_index += 1;
+                let next_player = self.participants[self.current_gift_index].clone();
+                self.gift_holders[self.current_gift_index] = Some(next_player.clone());
+                self.current_player = Some(next_player);
+                self.turn_deadline = Runtime::current_epoch() + self.turn_duration;
+            } else {
+                info!("Swap ended !");
+                for (i, holder) in self.gift_holders.iter().enumerate() {
+                    // Gifts can be any resource, so they may not have a name
+                    let metadata = borrow_resource_manager!(self.gifts[i].resource_address()).metadata();
+                    info!("{} has {}", holder.clone().unwrap(), metadata.get("name").map(String::as_str).unwrap_or("unnamed gift"));
+                }
+                self.phase = Phase::Ended;
+                self.current_player = None;
+            }
+        }
+
+        // Find the index of the gift held by a participant
+        fn gift_of(&self, participant: &NonFungibleId) -> usize {
+            match self.gift_holders.iter().position(|holder| holder.as_ref() == Some(participant)) {
+                Some(index) => index,
+                None => {
+                    info!("You are not participating in this game !");
+                    std::process::abort();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use scrypto::prelude::*;
+use crate::yankee_swap::*;
+
+// Create independent YankeeSwap games, so that every
+// office party can run its own game at the same time.
+blueprint! {
+    struct YankeeSwapFactory {
+        // Addresses of the games created by this factory
+        games: Vec<ComponentAddress>,
+        // Oracle used to price the gifts of the games with a minimum gift value
+        price_oracle: ComponentAddress,
+        // Resource in which the minimum gift values are expressed
+        value_quote: ResourceAddress,
+    }
+
+    impl YankeeSwapFactory {
+        pub fn new(price_oracle: ComponentAddress, value_quote: ResourceAddress) -> ComponentAddress {
+            Self {
+                games: Vec::new(),
+                price_oracle: price_oracle,
+                value_quote: value_quote,
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        // Create a new game and return its address and admin badge.
+        // Gifts must be worth at least `min_gift_value` to enter the game.
+        // Use 0 to accept any gift.
+        pub fn create_game(&mut self, max_steals: u32, allow_steal_back: bool, turn_duration: u64, max_participants: u32, entry_deadline: u64, min_gift_value: Decimal) -> (ComponentAddress, Bucket) {
+            assert!(min_gift_value >= Decimal::zero(), "The minimum gift value can't be negative !");
+
+            let (game_address, admin_badge): (ComponentAddress, Bucket) = YankeeSwap::new(max_steals, allow_steal_back, turn_duration, max_participants, entry_deadline);
+
+            if min_gift_value > Decimal::zero() {
+                let game: YankeeSwap = game_address.into();
+                admin_badge.authorize(|| {
+                    game.set_min_gift_value(self.price_oracle, self.value_quote, min_gift_value)
+                });
+            }
+
+            self.games.push(game_address);
+
+            (game_address, admin_badge)
+        }
+
+        // Return the games that participants can still enter
+        pub fn list_games(&self) -> Vec<ComponentAddress> {
+            self.games.iter()
+                .filter(|game_address| {
+                    let game: YankeeSwap = (**game_address).into();
+                    game.is_open()
+                })
+                .cloned()
+                .collect()
+        }
+    }
+}
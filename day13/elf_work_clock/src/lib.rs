@@ -1,4 +1,5 @@
 use scrypto::prelude::*;
+use sbor::*;
 
 import! {
     r#"
@@ -31,6 +32,18 @@ import! {
         }
       ],
       "methods": [
+        {
+          "name": "set_unix_time",
+          "mutability": "Mutable",
+          "inputs": [
+            {
+              "type": "U64"
+            }
+          ],
+          "output": {
+            "type": "Unit"
+          }
+        },
         {
           "name": "set_current_time",
           "mutability": "Mutable",
@@ -65,6 +78,62 @@ import! {
           "name": "get_time",
           "mutability": "Immutable",
           "inputs": [],
+          "output": {
+            "type": "Struct",
+            "name": "DateTime",
+            "fields": {
+              "type": "Named",
+              "named": [
+                [
+                  "year",
+                  {
+                    "type": "U16"
+                  }
+                ],
+                [
+                  "month",
+                  {
+                    "type": "U8"
+                  }
+                ],
+                [
+                  "day",
+                  {
+                    "type": "U8"
+                  }
+                ],
+                [
+                  "hour",
+                  {
+                    "type": "U8"
+                  }
+                ],
+                [
+                  "minute",
+                  {
+                    "type": "U8"
+                  }
+                ],
+                [
+                  "second",
+                  {
+                    "type": "U8"
+                  }
+                ],
+                [
+                  "second_since_unix",
+                  {
+                    "type": "U64"
+                  }
+                ]
+              ]
+            }
+          }
+        },
+        {
+          "name": "get_time_tuple",
+          "mutability": "Immutable",
+          "inputs": [],
           "output": {
             "type": "Tuple",
             "elements": [
@@ -93,88 +162,259 @@ import! {
           }
         }
       ]
+    }
     }"#
   }
 
+// Data stored on the elf badges
+#[derive(NonFungibleData)]
+pub struct ElfData {
+    // Epoch at which the elf was hired
+    hired_at: u64
+}
+
+// Data stored on the timesheets.
+// Can't be updated so that elves can't cheat on their start time.
+#[derive(NonFungibleData)]
+pub struct TimesheetData {
+    // Id of the badge of the elf working
+    elf: NonFungibleId,
+    // Unix time at which the shift started
+    started_at: u64
+}
+
+// Summary of the earnings of an elf
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe)]
+pub struct PayrollReport {
+    nb_shifts: u64,
+    // Minutes paid, including the overtime
+    minutes_worked: u64,
+    // Minutes paid with the overtime multiplier
+    overtime_minutes: u64,
+    // Salary paid to the elf
+    earned: Decimal
+}
+
 blueprint! {
     struct WorkClock {
         time_oracle: ComponentAddress,
         elf_badge: ResourceAddress,
-        time_sheet_minter: Vault,
+        // Used to mint the elf badges and to mint and burn the timesheets
+        minter: Vault,
+        // Resource definition of the timesheets
+        timesheet_def: ResourceAddress,
+        // Number of timesheets minted, used to generate their ids
+        nb_timesheets: u64,
+        // Maps elf badge ids to the timesheet of their current shift
+        open_shifts: HashMap<NonFungibleId, NonFungibleId>,
+        // Whether the salary is minted when paying the elves
+        // instead of being taken from the salary vault
+        mint_salary: bool,
+        // Vault containing the salary funded by the admin
         salary_vault: Vault,
-        hour_salary: Decimal
+        hour_salary: Decimal,
+        // Number of hours per day after which the overtime multiplier applies
+        overtime_threshold: u64,
+        overtime_multiplier: Decimal,
+        // Maximum number of hours paid for a single shift
+        max_shift_length: u64,
+        // Maps elf badge ids to the day and the number of minutes they worked that day
+        worked_today: HashMap<NonFungibleId, (u64, u64)>,
+        // Maps elf badge ids to a summary of their earnings
+        reports: HashMap<NonFungibleId, PayrollReport>
     }
 
     impl WorkClock {
-        pub fn new(nb_workers: u32, hour_salary: Decimal, time_oracle_address: ComponentAddress) -> (ComponentAddress, Bucket) {
-            let elf_badges = ResourceBuilder::new_fungible()
-                                    .divisibility(DIVISIBILITY_NONE)
+        // Create a work clock paying the elves with "Elf Salary" tokens minted at the end of each shift
+        pub fn new(nb_workers: u32, hour_salary: Decimal, time_oracle_address: ComponentAddress, overtime_threshold: u64, overtime_multiplier: Decimal, max_shift_length: u64) -> (ComponentAddress, Bucket, Bucket) {
+            Self::instantiate_clock(nb_workers, hour_salary, time_oracle_address, overtime_threshold, overtime_multiplier, max_shift_length, None)
+        }
+
+        // Create a work clock paying the elves with the tokens the admin sends through `fund_payroll`
+        pub fn new_funded(nb_workers: u32, hour_salary: Decimal, time_oracle_address: ComponentAddress, overtime_threshold: u64, overtime_multiplier: Decimal, max_shift_length: u64, salary_resource: ResourceAddress) -> (ComponentAddress, Bucket, Bucket) {
+            Self::instantiate_clock(nb_workers, hour_salary, time_oracle_address, overtime_threshold, overtime_multiplier, max_shift_length, Some(salary_resource))
+        }
+
+        // Returns the component, the elf badges and the admin badge
+        fn instantiate_clock(nb_workers: u32, hour_salary: Decimal, time_oracle_address: ComponentAddress, overtime_threshold: u64, overtime_multiplier: Decimal, max_shift_length: u64, salary_resource: Option<ResourceAddress>) -> (ComponentAddress, Bucket, Bucket) {
+            assert!(overtime_multiplier >= Decimal::one(), "The overtime multiplier must be at least 1 !");
+            assert!(max_shift_length > 0, "The maximum shift length must be positive !");
+
+            // Used to create the elf badges and to create and burn time sheets
+            let minter = ResourceBuilder::new_fungible()
+                                .divisibility(DIVISIBILITY_NONE)
+                                .metadata("name", "WorkClock minter")
+                                .initial_supply(1);
+
+            // Used to fund the payroll
+            let admin_badge = ResourceBuilder::new_fungible()
+                                .divisibility(DIVISIBILITY_NONE)
+                                .metadata("name", "WorkClock Admin")
+                                .initial_supply(1);
+            let elf_badge_def = ResourceBuilder::new_non_fungible()
                                     .metadata("name", "Elf Badge")
-                                    .initial_supply(nb_workers);
+                                    .mintable(rule!(require(minter.resource_address())), LOCKED)
+                                    .no_initial_supply();
 
-            // Used to create and burn time sheets
-            let time_sheet_minter = ResourceBuilder::new_fungible()
-                                        .divisibility(DIVISIBILITY_NONE)
-                                        .metadata("name", "TimeSheet minter")
-                                        .initial_supply(1);
+            // Timesheets can only be created and burned by the component
+            let timesheet_def = ResourceBuilder::new_non_fungible()
+                                    .metadata("name", "TimeSheet")
+                                    .mintable(rule!(require(minter.resource_address())), LOCKED)
+                                    .burnable(rule!(require(minter.resource_address())), LOCKED)
+                                    .no_initial_supply();
 
-            // Create the tokens that will be used to pay the elfs
-            let salary_tokens = ResourceBuilder::new_fungible()
-                                    .metadata("name", "Elf Salary")
-                                    .initial_supply(100000000000_u64);
+            // Mint a badge for each worker
+            let elf_badges = minter.authorize(|| {
+                let mut elf_badges = Bucket::new(elf_badge_def);
+                for i in 1..=nb_workers {
+                    elf_badges.put(borrow_resource_manager!(elf_badge_def)
+                        .mint_non_fungible(&NonFungibleId::from_u64(i as u64), ElfData {
+                            hired_at: Runtime::current_epoch()
+                        }));
+                }
+                elf_badges
+            });
+
+            // Create the tokens that will be used to pay the elfs,
+            // unless the admin funds the payroll with their own tokens
+            let mint_salary = salary_resource.is_none();
+            let salary_resource = match salary_resource {
+                Some(salary_resource) => salary_resource,
+                None => ResourceBuilder::new_fungible()
+                            .metadata("name", "Elf Salary")
+                            .mintable(rule!(require(minter.resource_address())), LOCKED)
+                            .no_initial_supply()
+            };
 
             let component = Self {
                 time_oracle: time_oracle_address.into(),
-                elf_badge: elf_badges.resource_address(),
-                time_sheet_minter: Vault::with_bucket(time_sheet_minter),
-                salary_vault: Vault::with_bucket(salary_tokens),
-                hour_salary: hour_salary
+                elf_badge: elf_badge_def,
+                minter: Vault::with_bucket(minter),
+                timesheet_def: timesheet_def,
+                nb_timesheets: 0,
+                open_shifts: HashMap::new(),
+                mint_salary: mint_salary,
+                salary_vault: Vault::new(salary_resource),
+                hour_salary: hour_salary,
+                overtime_threshold: overtime_threshold,
+                overtime_multiplier: overtime_multiplier,
+                max_shift_length: max_shift_length,
+                worked_today: HashMap::new(),
+                reports: HashMap::new()
             }
             .instantiate();
 
             let access_rules = AccessRules::new()
-              .method("start_work", rule!(require(elf_badges.resource_address())))
+              .method("fund_payroll", rule!(require(admin_badge.resource_address())))
               .default(rule!(allow_all));
 
-            (component.add_access_check(access_rules).globalize(), elf_badges)
+            (component.add_access_check(access_rules).globalize(), elf_badges, admin_badge)
         }
 
-        pub fn start_work(&self) -> Bucket {
-            // Get the time. Send empty bucket as fee
+        // Start a shift. Returns a timesheet that will
+        // allow the elf to get paid at the end of the shift.
+        pub fn start_work(&mut self, elf_badge: Proof) -> Bucket {
+            assert!(elf_badge.resource_address() == self.elf_badge, "Invalid elf badge");
+            let elf_id = elf_badge.non_fungible::<ElfData>().id();
+            elf_badge.drop();
+
+            assert!(!self.open_shifts.contains_key(&elf_id), "You already started working, end your shift first !");
+
+            // Get the time
             let time_oracle: UTCTimeOracle = self.time_oracle.into();
-            let (year, month, day, hour, minute, second, unix_time) = time_oracle.get_time();
-
-            // Create a timesheet token
-            ResourceBuilder::new_fungible()
-                    .divisibility(DIVISIBILITY_NONE)
-                    .metadata("name", format!("TimeSheet {}/{}/{} {}:{}:{}", year, month, day, hour, minute, second))
-                    .metadata("date", format!("{}", unix_time))
-                    .burnable(rule!(require(self.time_sheet_minter.resource_address())), LOCKED)
-                    .initial_supply(1)
+            let (year, month, day, hour, minute, second, unix_time) = time_oracle.get_time_tuple();
+            info!("Shift started on {}/{}/{} {}:{}:{}", year, month, day, hour, minute, second);
+
+            // Create a timesheet NFT
+            self.nb_timesheets += 1;
+            let timesheet_id = NonFungibleId::from_u64(self.nb_timesheets);
+            self.open_shifts.insert(elf_id.clone(), timesheet_id.clone());
+
+            let timesheet_def = self.timesheet_def;
+            self.minter.authorize(|| {
+                borrow_resource_manager!(timesheet_def).mint_non_fungible(&timesheet_id, TimesheetData {
+                    elf: elf_id,
+                    started_at: unix_time
+                })
+            })
         }
 
         pub fn end_work(&mut self, timesheet: Bucket) -> Bucket {
-            assert!(timesheet.amount() > Decimal::zero(), "Missing timesheet");
+            // Make sure the timesheet was created by this component
+            assert!(timesheet.resource_address() == self.timesheet_def, "Invalid timesheet");
+            assert!(timesheet.amount() == Decimal::one(), "Provide a single timesheet");
 
             let time_oracle: UTCTimeOracle = self.time_oracle.into();
 
             // Get the current time
-            let (_, _, _, _, _, _, unix_time) = time_oracle.get_time();
+            let (_, _, _, _, _, _, unix_time) = time_oracle.get_time_tuple();
 
-            // No checks here when unwrapping to keep it simple.
-            // Keep in mind that anyone could create their own badge to fake the time
-            // they started working. I didn't want to do fix this since NFTs have not been implemented yet
-            // and they could easily fix this issue.
-            let start_time: u64 = borrow_resource_manager!(timesheet.resource_address()).metadata().get("date").unwrap().parse().unwrap();
+            let data: TimesheetData = timesheet.non_fungible::<TimesheetData>().data();
+            let start_time = data.started_at;
+            self.open_shifts.remove(&data.elf);
 
             // Burn the timesheet
-            self.time_sheet_minter.authorize(|| {
+            self.minter.authorize(|| {
                 timesheet.burn();
             });
 
-            let hours_worked = (unix_time - start_time) / 3600;
+            // Pay by the minute, up to the maximum shift length
+            let minutes_worked = std::cmp::min(unix_time - start_time, self.max_shift_length * 3600) / 60;
+
+            // Minutes worked beyond the daily threshold are overtime.
+            // Shifts count for the day they started.
+            let day = start_time / 86400;
+            let worked_today = self.worked_today.entry(data.elf.clone()).or_insert((day, 0));
+            if worked_today.0 != day {
+                *worked_today = (day, 0);
+            }
+            let regular_left = (self.overtime_threshold * 60).saturating_sub(worked_today.1);
+            let overtime_minutes = minutes_worked.saturating_sub(regular_left);
+            worked_today.1 += minutes_worked;
+
+            // Multiply before dividing so that no salary is lost to rounding
+            let salary = self.hour_salary * Decimal::from(minutes_worked - overtime_minutes) / 60
+                + self.hour_salary * self.overtime_multiplier * Decimal::from(overtime_minutes) / 60;
+
+            let report = self.reports.entry(data.elf).or_insert(PayrollReport {
+                nb_shifts: 0,
+                minutes_worked: 0,
+                overtime_minutes: 0,
+                earned: Decimal::zero()
+            });
+            report.nb_shifts += 1;
+            report.minutes_worked += minutes_worked;
+            report.overtime_minutes += overtime_minutes;
+            report.earned += salary;
+
             // Send the salary
-            self.salary_vault.take(self.hour_salary * hours_worked)
+            if self.mint_salary {
+                let salary_resource = self.salary_vault.resource_address();
+                self.minter.authorize(|| {
+                    borrow_resource_manager!(salary_resource).mint(salary)
+                })
+            } else {
+                assert!(self.salary_vault.amount() >= salary, "Not enough funds in the payroll, ask the admin to fund it !");
+                self.salary_vault.take(salary)
+            }
+        }
+
+        // As admin, add tokens to the salary vault
+        pub fn fund_payroll(&mut self, funds: Bucket) {
+            self.salary_vault.put(funds);
+        }
+
+        // Summarise the earnings of the elf with the provided badge id
+        pub fn payroll_report(&self, elf: NonFungibleId) -> PayrollReport {
+            match self.reports.get(&elf) {
+                Some(report) => report.clone(),
+                None => PayrollReport {
+                    nb_shifts: 0,
+                    minutes_worked: 0,
+                    overtime_minutes: 0,
+                    earned: Decimal::zero()
+                }
+            }
         }
     }
 }
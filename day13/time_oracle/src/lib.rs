@@ -1,17 +1,25 @@
 use scrypto::prelude::*;
+use sbor::*;
+
+// Calendar date and time, in UTC
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe)]
+pub struct DateTime {
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    second_since_unix: u64
+}
 
 blueprint! {
     struct UTCTimeOracle {
         // Used to update the time
         admin_badge: ResourceAddress,
-        
-        year: u16,
-        month: u8,
-        day: u8,
-        hour: u8,
-        minute: u8,
-        second: u8,
-        second_since_unix: u64
+
+        // Current time, derived from the unix time set by the admins
+        time: DateTime
     }
 
     impl UTCTimeOracle {
@@ -24,17 +32,12 @@ blueprint! {
 
             let component = Self {
                 admin_badge: admin_badges.resource_address(),
-                year: 0,
-                month: 0,
-                day: 0,
-                hour: 0,
-                minute: 0,
-                second: 0,
-                second_since_unix: 0
+                time: Self::date_time_from_unix(0)
             }
             .instantiate();
 
             let auth_rules = AccessRules::new()
+                .method("set_unix_time", rule!(require(admin_badges.resource_address())))
                 .method("set_current_time", rule!(require(admin_badges.resource_address())))
                 .default(rule!(allow_all));
 
@@ -42,19 +45,60 @@ blueprint! {
             (component.add_access_check(auth_rules).globalize(), admin_badges)
         }
 
+        // Set the time from the number of seconds since 1970-01-01 00:00:00 UTC.
+        // The calendar fields are computed from it and time can't go backwards.
+        pub fn set_unix_time(&mut self, second_since_unix: u64) {
+            assert!(second_since_unix >= self.time.second_since_unix, "Time can't go backwards !");
+            self.time = Self::date_time_from_unix(second_since_unix);
+        }
+
+        // Kept for compatibility, prefer `set_unix_time`.
+        // The calendar fields must match `second_since_unix`.
         pub fn set_current_time(&mut self, year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8, second_since_unix: u64) {
-            self.year = year;
-            self.month = month;
-            self.day = day;
-            self.hour = hour;
-            self.minute = minute;
-            self.second = second;
-            self.second_since_unix = second_since_unix;
+            let time = Self::date_time_from_unix(second_since_unix);
+            assert!(
+                (time.year, time.month, time.day, time.hour, time.minute, time.second) == (year, month, day, hour, minute, second),
+                "The date does not match the unix time !"
+            );
+
+            self.set_unix_time(second_since_unix);
         }
 
-        pub fn get_time(&self) -> (u16, u8, u8, u8, u8, u8, u64) {
+        pub fn get_time(&self) -> DateTime {
+            self.time.clone()
+        }
+
+        // Kept for compatibility, prefer `get_time`
+        pub fn get_time_tuple(&self) -> (u16, u8, u8, u8, u8, u8, u64) {
             // Return the datetime
-            (self.year, self.month, self.day, self.hour, self.minute, self.second, self.second_since_unix)
+            (self.time.year, self.time.month, self.time.day, self.time.hour, self.time.minute, self.time.second, self.time.second_since_unix)
+        }
+
+        // Compute the calendar fields from the unix time with
+        // Howard Hinnant's civil_from_days algorithm
+        fn date_time_from_unix(second_since_unix: u64) -> DateTime {
+            let seconds_of_day = second_since_unix % 86400;
+
+            // Shift the epoch to 0000-03-01 so that leap days are at the end of the year
+            let z = second_since_unix / 86400 + 719468;
+            let era = z / 146097;
+            let day_of_era = z - era * 146097;
+            let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+            let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+            let mp = (5 * day_of_year + 2) / 153;
+            let day = day_of_year - (153 * mp + 2) / 5 + 1;
+            let month = if mp < 10 { mp + 3 } else { mp - 9 };
+            let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+            DateTime {
+                year: year as u16,
+                month: month as u8,
+                day: day as u8,
+                hour: (seconds_of_day / 3600) as u8,
+                minute: (seconds_of_day % 3600 / 60) as u8,
+                second: (seconds_of_day % 60) as u8,
+                second_since_unix: second_since_unix
+            }
         }
     }
 }
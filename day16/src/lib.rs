@@ -157,7 +157,7 @@ blueprint! {
         // The following methods are used to randomly generate an elf
 
         fn random_head(&mut self) -> Head {
-            match self.random_number(0, 3) {
+            match random::range(&mut Runtime::generate_uuid, 0, 4) {
                 0 => Head::Cap,
                 1 => Head::ChristmasHat,
                 2 => Head::Crown,
@@ -167,7 +167,7 @@ blueprint! {
         }
 
         fn random_clothing(&mut self) -> Clothing {
-            match self.random_number(0, 3) {
+            match random::range(&mut Runtime::generate_uuid, 0, 4) {
                 0 => Clothing::Hoodie,
                 1 => Clothing::SantaCoat,
                 2 => Clothing::WhiteShirt,
@@ -177,7 +177,7 @@ blueprint! {
         }
 
         fn random_mouth(&mut self) -> Mouth {
-            match self.random_number(0, 4) {
+            match random::range(&mut Runtime::generate_uuid, 0, 5) {
                 0 => Mouth::Smile,
                 1 => Mouth::Sad,
                 2 => Mouth::TongueOut,
@@ -188,7 +188,7 @@ blueprint! {
         }
 
         fn random_nose(&mut self) -> Nose {
-            match self.random_number(0, 2) {
+            match random::range(&mut Runtime::generate_uuid, 0, 3) {
                 0 => Nose::Regular,
                 1 => Nose::Clown,
                 2 => Nose::Runny,
@@ -197,7 +197,7 @@ blueprint! {
         }
 
         fn random_eye(&mut self) -> Eyewear {
-            match self.random_number(0, 3) {
+            match random::range(&mut Runtime::generate_uuid, 0, 4) {
                 0 => Eyewear::None,
                 1 => Eyewear::EyePatch,
                 2 => Eyewear::ReadingGlasses,
@@ -207,7 +207,7 @@ blueprint! {
         }
 
         fn random_background(&mut self) -> Background {
-            match self.random_number(0, 4) {
+            match random::range(&mut Runtime::generate_uuid, 0, 5) {
                 0 => Background::White,
                 1 => Background::Blue,
                 2 => Background::Gold,
@@ -218,15 +218,8 @@ blueprint! {
         }
 
         fn random_color(&mut self) -> usize {
-            self.random_number(0, 16777215)
-        }
-
-        // Generate a random number
-        // WARNING: DON'T USE THIS IN PRODUCTION !
-        fn random_number(&mut self, min: u64, max: u64) -> usize {
-            let mut random_number = Runtime::generate_uuid() as u64;
-            random_number = (random_number / u64::MAX) * (max - min) + min;
-            random_number as usize
+            // Any 24 bits RGB color
+            random::range(&mut Runtime::generate_uuid, 0, 16777216) as usize
         }
     }
 }
\ No newline at end of file
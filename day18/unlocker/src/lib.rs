@@ -9,11 +9,33 @@ r#"
   "functions": [
     {
       "name": "new",
-      "inputs": [],
+      "inputs": [
+        {
+          "type": "U32"
+        },
+        {
+          "type": "U64"
+        },
+        {
+          "type": "Custom",
+          "name": "Decimal",
+          "generics": []
+        }
+      ],
       "output": {
-        "type": "Custom",
-        "name": "ComponentAddress",
-        "generics": []
+        "type": "Tuple",
+        "elements": [
+          {
+            "type": "Custom",
+            "name": "ComponentAddress",
+            "generics": []
+          },
+          {
+            "type": "Custom",
+            "name": "Bucket",
+            "generics": []
+          }
+        ]
       }
     }
   ],
@@ -42,6 +64,112 @@ r#"
         }
       }
     },
+    {
+      "name": "get_price_with_age",
+      "mutability": "Immutable",
+      "inputs": [
+        {
+          "type": "Custom",
+          "name": "ResourceAddress",
+          "generics": []
+        },
+        {
+          "type": "Custom",
+          "name": "ResourceAddress",
+          "generics": []
+        }
+      ],
+      "output": {
+        "type": "Option",
+        "value": {
+          "type": "Tuple",
+          "elements": [
+            {
+              "type": "Custom",
+              "name": "Decimal",
+              "generics": []
+            },
+            {
+              "type": "U64"
+            }
+          ]
+        }
+      }
+    },
+    {
+      "name": "get_price_fresh",
+      "mutability": "Immutable",
+      "inputs": [
+        {
+          "type": "Custom",
+          "name": "ResourceAddress",
+          "generics": []
+        },
+        {
+          "type": "Custom",
+          "name": "ResourceAddress",
+          "generics": []
+        },
+        {
+          "type": "U64"
+        }
+      ],
+      "output": {
+        "type": "Custom",
+        "name": "Decimal",
+        "generics": []
+      }
+    },
+    {
+      "name": "get_twap",
+      "mutability": "Immutable",
+      "inputs": [
+        {
+          "type": "Custom",
+          "name": "ResourceAddress",
+          "generics": []
+        },
+        {
+          "type": "Custom",
+          "name": "ResourceAddress",
+          "generics": []
+        },
+        {
+          "type": "U64"
+        }
+      ],
+      "output": {
+        "type": "Option",
+        "value": {
+          "type": "Custom",
+          "name": "Decimal",
+          "generics": []
+        }
+      }
+    },
+    {
+      "name": "list_pairs",
+      "mutability": "Immutable",
+      "inputs": [],
+      "output": {
+        "type": "Vec",
+        "element": {
+          "type": "Tuple",
+          "elements": [
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            },
+            {
+              "type": "Custom",
+              "name": "ResourceAddress",
+              "generics": []
+            }
+          ]
+        }
+      }
+    },
     {
       "name": "get_usd_address",
       "mutability": "Immutable",
@@ -54,7 +182,7 @@ r#"
     },
     {
       "name": "update_price",
-      "mutability": "Immutable",
+      "mutability": "Mutable",
       "inputs": [
         {
           "type": "Custom",
@@ -70,6 +198,91 @@ r#"
           "type": "Custom",
           "name": "Decimal",
           "generics": []
+        },
+        {
+          "type": "Custom",
+          "name": "Proof",
+          "generics": []
+        }
+      ],
+      "output": {
+        "type": "Unit"
+      }
+    },
+    {
+      "name": "add_reporter",
+      "mutability": "Mutable",
+      "inputs": [
+        {
+          "type": "String"
+        }
+      ],
+      "output": {
+        "type": "Custom",
+        "name": "Bucket",
+        "generics": []
+      }
+    },
+    {
+      "name": "remove_reporter",
+      "mutability": "Mutable",
+      "inputs": [
+        {
+          "type": "Custom",
+          "name": "NonFungibleId",
+          "generics": []
+        }
+      ],
+      "output": {
+        "type": "Unit"
+      }
+    },
+    {
+      "name": "set_min_reporters",
+      "mutability": "Mutable",
+      "inputs": [
+        {
+          "type": "U32"
+        }
+      ],
+      "output": {
+        "type": "Unit"
+      }
+    },
+    {
+      "name": "set_submission_window",
+      "mutability": "Mutable",
+      "inputs": [
+        {
+          "type": "U64"
+        }
+      ],
+      "output": {
+        "type": "Unit"
+      }
+    },
+    {
+      "name": "set_max_deviation",
+      "mutability": "Mutable",
+      "inputs": [
+        {
+          "type": "Custom",
+          "name": "Decimal",
+          "generics": []
+        }
+      ],
+      "output": {
+        "type": "Unit"
+      }
+    },
+    {
+      "name": "set_pivot",
+      "mutability": "Mutable",
+      "inputs": [
+        {
+          "type": "Custom",
+          "name": "ResourceAddress",
+          "generics": []
         }
       ],
       "output": {
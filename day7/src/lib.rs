@@ -1,72 +1,163 @@
 use scrypto::prelude::*;
 
-// ElfWorkshop component. 
+// Data stored on each elf badge
+#[derive(NonFungibleData)]
+pub struct ElfData {
+    hired_at: u64
+}
+
+// ElfWorkshop component.
 // People can register as elf to receive a badge.
 // They can then use the badge to create new toys and the component
 // keeps track of the amount of toys each elf created.
+// Elves earn a wage for every toy they create, paid from
+// a payroll vault funded by the admin.
 blueprint! {
     struct ElfWorkshop {
         // Vault that will contain the badge allowing this component to mint new elf_badges
         elf_badge_minter: Vault,
         // Resource definition of the elf badges
         elf_badge: ResourceAddress,
-        // Maps elf's badge to an hashmap mapping toy name to quantity
-        toys: HashMap<ResourceAddress, HashMap<String, u32>>
+        // Maps elf's badge id to an hashmap mapping toy name to quantity
+        toys: HashMap<NonFungibleId, HashMap<String, u32>>,
+        // Number of elves that joined the workshop
+        nb_elves: u64,
+        // Vault containing the tokens used to pay the elves
+        payroll: Vault,
+        // Maps toy name to the wage paid for each toy created
+        toy_rates: HashMap<String, Decimal>,
+        // Maximum number of toys an elf can create per epoch
+        quota_per_epoch: u32,
+        // Maps elf's badge id to the epoch and number of toys created during that epoch
+        production: HashMap<NonFungibleId, (u64, u32)>,
+        // Maps elf's badge id to the wages they earned but did not claim yet
+        unpaid_wages: HashMap<NonFungibleId, Decimal>
     }
 
     impl ElfWorkshop {
-        pub fn new() -> ComponentAddress {
+        pub fn new(wage_resource: ResourceAddress, quota_per_epoch: u32) -> (ComponentAddress, Bucket) {
             // Create a badge allowing this component to mint new elf badges
             let elf_badge_minter: Bucket = ResourceBuilder::new_fungible()
                 .divisibility(DIVISIBILITY_NONE)
                 .metadata("name", "Elf badge minter")
                 .initial_supply(1);
 
+            // Create the admin badge, used to set the rates and fund the payroll
+            let admin_badge: Bucket = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "ElfWorkshop Admin")
+                .initial_supply(1);
+
             // Define a mintable resource representing the elf badges
             // Only people presenting the elf_badge_minter badge can mint this resource.
             // The LOCKED flag makes sure that we cannot update this authorization rule.
-            let elf_badges: ResourceAddress = ResourceBuilder::new_fungible()
-                .divisibility(DIVISIBILITY_NONE)
+            // Each elf receives its own NFT so that we can track
+            // their production and wages individually.
+            let elf_badges: ResourceAddress = ResourceBuilder::new_non_fungible()
                 .metadata("name", "Elf Badge")
                 .mintable(auth!(require(elf_badge_minter.resource_address())), LOCKED)
                 .no_initial_supply();
 
             // Instantiate the component
-            Self {
+            let component = Self {
                 elf_badge_minter: Vault::with_bucket(elf_badge_minter),
                 elf_badge: elf_badges,
-                toys: HashMap::new()
+                toys: HashMap::new(),
+                nb_elves: 0,
+                payroll: Vault::new(wage_resource),
+                toy_rates: HashMap::new(),
+                quota_per_epoch: quota_per_epoch,
+                production: HashMap::new(),
+                unpaid_wages: HashMap::new()
             }
-            .instantiate().globalize()
+            .instantiate();
+
+            // Only the admin can configure the wages and quota
+            let auth = AccessRules::new()
+                .method("set_toy_rate", auth!(require(admin_badge.resource_address())))
+                .method("set_quota", auth!(require(admin_badge.resource_address())))
+                .method("fund_payroll", auth!(require(admin_badge.resource_address())))
+                .default(auth!(allow_all));
+
+            (component.add_access_check(auth).globalize(), admin_badge)
         }
 
         pub fn become_elf(&mut self) -> Bucket {
             info!("Welcome to the factory, here is your badge");
 
+            self.nb_elves += 1;
+
             // Mint a new badge and send it to the caller
             // Vault.authorize takes the badge from the vault and puts it
             // on the component's auth zone.
             self.elf_badge_minter.authorize(|| {
-                borrow_resource_manager!(self.elf_badge).mint(1)
+                borrow_resource_manager!(self.elf_badge)
+                    .mint_non_fungible(&NonFungibleId::from_u64(self.nb_elves), ElfData {
+                        hired_at: Runtime::current_epoch()
+                    })
             })
         }
 
         pub fn create_toy(&mut self, name: String, badge: Proof) {
             assert!(badge.resource_address() == self.elf_badge, "That's not a valid bage !");
-            
-            // The badge's address is used to identify the elf
-            let elf_id = badge.resource_address();
+
+            // The badge's id is used to identify the elf
+            let elf_id = badge.non_fungible::<ElfData>().id();
 
             // We always need to drop bucket refs or else we get an error !
             badge.drop();
 
+            // Make sure the elf did not reach its quota for this epoch.
+            // The counter is reset when a new epoch starts.
+            let current_epoch = Runtime::current_epoch();
+            let (epoch, produced) = self.production.entry(elf_id.clone()).or_insert((current_epoch, 0));
+            if *epoch != current_epoch {
+                *epoch = current_epoch;
+                *produced = 0;
+            }
+            assert!(*produced < self.quota_per_epoch, "You reached your quota for this epoch, come back later !");
+            *produced += 1;
+
             // Insert the toy in the hashmap
-            let elf_toys = self.toys.entry(elf_id).or_insert(HashMap::new());
+            let elf_toys = self.toys.entry(elf_id.clone()).or_insert(HashMap::new());
             let old_count = *elf_toys.entry(name.clone()).or_insert(0);
             elf_toys.insert(name.clone(), old_count + 1);
 
+            // Add the wage for this toy to the elf's unpaid wages
+            let rate = *self.toy_rates.get(&name).unwrap_or(&Decimal::zero());
+            *self.unpaid_wages.entry(elf_id).or_insert(Decimal::zero()) += rate;
+
             info!("The total amount of {} you created is {}", name, old_count + 1)
         }
-        
+
+        // Send the wages earned by the elf presenting the badge
+        pub fn claim_wages(&mut self, badge: Proof) -> Bucket {
+            assert!(badge.resource_address() == self.elf_badge, "That's not a valid bage !");
+
+            let elf_id = badge.non_fungible::<ElfData>().id();
+            badge.drop();
+
+            let amount = self.unpaid_wages.remove(&elf_id).unwrap_or(Decimal::zero());
+            assert!(self.payroll.amount() >= amount, "Not enough funds in the payroll, ask the admin to fund it !");
+
+            info!("Here are your {} tokens, thank you for your work !", amount);
+            self.payroll.take(amount)
+        }
+
+        // Set the wage paid to the elves for each toy of that type
+        pub fn set_toy_rate(&mut self, name: String, rate: Decimal) {
+            assert!(rate >= Decimal::zero(), "The rate can't be negative");
+            self.toy_rates.insert(name, rate);
+        }
+
+        // Set the maximum number of toys an elf can create per epoch
+        pub fn set_quota(&mut self, quota_per_epoch: u32) {
+            self.quota_per_epoch = quota_per_epoch;
+        }
+
+        // Add tokens to the payroll vault
+        pub fn fund_payroll(&mut self, funds: Bucket) {
+            self.payroll.put(funds);
+        }
     }
 }
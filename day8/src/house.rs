@@ -2,56 +2,158 @@ use scrypto::prelude::*;
 
 blueprint! {
     struct House {
+        // Resource definition of the current key badge.
+        // Changes every time the homeowner rotates the key.
         key: ResourceAddress,
+        // Resource definition of the badge held by the homeowner
+        owner_badge: ResourceAddress,
+        // Badge allowing the component to mint more milk and cookies
+        snack_minter: Vault,
         milk: Vault,
         cookies: Vault,
-        gifts: Vault
+        // Gift resources the homeowner accepts under the tree
+        accepted_gifts: Vec<ResourceAddress>,
+        // Maps gift resource to the vault containing the received gifts
+        gifts: HashMap<ResourceAddress, Vault>
     }
 
     impl House {
-        pub fn new(gift_resource: ResourceAddress) -> (ComponentAddress, Bucket) {
+        // Instantiate a new house.
+        // Returns the owner badge, kept by the homeowner, and the key
+        // badge, which is meant to be delegated to Santa
+        pub fn new(gift_resource: ResourceAddress) -> (ComponentAddress, Bucket, Bucket) {
             // Create a key badge, allowing people to call methods on this component
             let key = ResourceBuilder::new_fungible()
                         .divisibility(DIVISIBILITY_NONE)
                         .metadata("name", "House Key")
                         .initial_supply(1);
 
+            // Create the badge identifying the owner of the house
+            let owner_badge = ResourceBuilder::new_fungible()
+                        .divisibility(DIVISIBILITY_NONE)
+                        .metadata("name", "House Owner Badge")
+                        .initial_supply(1);
+
+            // Create the badge allowing the component to restock the snacks
+            let snack_minter = ResourceBuilder::new_fungible()
+                        .divisibility(DIVISIBILITY_NONE)
+                        .metadata("name", "House Snack Minter")
+                        .initial_supply(1);
+
             // Create the milk and cookie tokens
             let milk = ResourceBuilder::new_fungible()
                         .divisibility(DIVISIBILITY_NONE)
                         .metadata("name", "Milk")
+                        .mintable(auth!(require(snack_minter.resource_address())), LOCKED)
                         .initial_supply(1);
             let cookies = ResourceBuilder::new_fungible()
                             .divisibility(DIVISIBILITY_NONE)
                             .metadata("name", "Cookie")
+                            .mintable(auth!(require(snack_minter.resource_address())), LOCKED)
                             .initial_supply(3);
 
             let component = Self {
                 // Store the resource definition of
                 // the key badge to securise the methods
                 key: key.resource_address(),
+                owner_badge: owner_badge.resource_address(),
+                snack_minter: Vault::with_bucket(snack_minter),
                 milk: Vault::with_bucket(milk),
                 cookies: Vault::with_bucket(cookies),
-                gifts: Vault::new(gift_resource) // Instantiate empty gift vault
+                // By default, only accept the gifts from Santa
+                accepted_gifts: vec![gift_resource],
+                gifts: HashMap::new()
             }.instantiate();
 
-            // Make sure only people presenting the `key` badge are
-            // able to call the two methods
+            // Make sure only the homeowner can manage the house.
+            // The methods requiring the `key` badge check it themselves,
+            // since the key can change when the homeowner rotates it.
             let auth = AccessRules::new()
-                .method("get_milk_and_cookie", auth!(require(key.resource_address())))
-                .method("give_gift", auth!(require(key.resource_address())));
+                .method("rotate_key", auth!(require(owner_badge.resource_address())))
+                .method("restock_snacks", auth!(require(owner_badge.resource_address())))
+                .method("set_accepted_gifts", auth!(require(owner_badge.resource_address())))
+                .method("get_gifts", auth!(require(owner_badge.resource_address())))
+                .default(auth!(allow_all));
 
-            (component.add_access_check(auth).globalize(), key)
+            (component.add_access_check(auth).globalize(), owner_badge, key)
         }
 
-        pub fn get_milk_and_cookie(&mut self) -> (Bucket, Bucket) {
+        pub fn get_milk_and_cookie(&mut self, key: Proof) -> (Bucket, Bucket) {
+            assert!(key.resource_address() == self.key, "Wrong key !");
+            key.drop();
+
             // Give the cookies and milk
             (self.cookies.take_all(), self.milk.take_all())
         }
 
-        pub fn give_gift(&mut self, gift: Bucket) {
+        // Put the gift under the tree if the homeowner accepts it.
+        // Rejected gifts are returned to the caller.
+        pub fn give_gift(&mut self, gift: Bucket, key: Proof) -> Bucket {
+            assert!(key.resource_address() == self.key, "Wrong key !");
+            key.drop();
+
+            let gift_resource = gift.resource_address();
+            if !self.accepted_gifts.contains(&gift_resource) {
+                info!("No thank you, we don't want this gift !");
+                return gift;
+            }
+
             // Insert the gift in the component's vault
-            self.gifts.put(gift);
+            self.gifts.entry(gift_resource).or_insert(Vault::new(gift_resource)).put(gift);
+            Bucket::new(gift_resource)
+        }
+
+        // Create a new key and invalidate the old one.
+        // The new key has to be given to Santa for him to come in.
+        pub fn rotate_key(&mut self) -> Bucket {
+            let key = ResourceBuilder::new_fungible()
+                        .divisibility(DIVISIBILITY_NONE)
+                        .metadata("name", "House Key")
+                        .initial_supply(1);
+
+            self.key = key.resource_address();
+            key
+        }
+
+        // Bake more cookies and pour more milk for the next visit
+        pub fn restock_snacks(&mut self, milk: Decimal, cookies: Decimal) {
+            let (new_milk, new_cookies) = self.snack_minter.authorize(|| {
+                (
+                    borrow_resource_manager!(self.milk.resource_address()).mint(milk),
+                    borrow_resource_manager!(self.cookies.resource_address()).mint(cookies)
+                )
+            });
+
+            self.milk.put(new_milk);
+            self.cookies.put(new_cookies);
+        }
+
+        // Set the list of gift resources the homeowner accepts
+        pub fn set_accepted_gifts(&mut self, accepted_gifts: Vec<ResourceAddress>) {
+            self.accepted_gifts = accepted_gifts;
+        }
+
+        // Tell the homeowner what is under the tree
+        pub fn get_gifts(&self) -> HashMap<ResourceAddress, Decimal> {
+            self.gifts.iter()
+                .map(|(resource, vault)| (*resource, vault.amount()))
+                .collect()
+        }
+
+        // Used by Santa to know if there is something left to eat
+        pub fn has_snacks(&self) -> bool {
+            !self.milk.is_empty() || !self.cookies.is_empty()
+        }
+
+        // Used by Santa to make sure the key he receives opens this house
+        pub fn get_key_resource(&self) -> ResourceAddress {
+            self.key
+        }
+
+        // Used by Santa to make sure the person registering
+        // or deregistering the house is its owner
+        pub fn get_owner_badge(&self) -> ResourceAddress {
+            self.owner_badge
         }
     }
-}
\ No newline at end of file
+}
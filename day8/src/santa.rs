@@ -1,12 +1,31 @@
+use sbor::*;
 use scrypto::prelude::*;
 use crate::house::House;
 
+// Result of Santa's visit to a house of his route
+#[derive(Debug, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    // A gift was left and the milk and cookies were collected
+    Delivered,
+    // The house had no milk or cookies left, Santa did not go in
+    SkippedEmpty,
+    // The milk and cookies were collected but the homeowner refused the gift
+    GiftRejected,
+    // No house is registered with this id, or the key
+    // Santa holds for it has been rotated by the homeowner
+    MissingKey,
+    // Santa's bag was empty, he did not go in
+    OutOfGifts
+}
+
 blueprint! {
     struct Santa {
-        // List of House components
-        houses: Vec<ComponentAddress>,
-        // Badges required to access the houses
-        keys: Vec<Vault>,
+        // Maps house ids to the registered House components
+        houses: HashMap<u64, ComponentAddress>,
+        // Maps house ids to the badges required to access the houses
+        keys: HashMap<u64, Vault>,
+        // Id given to the next registered house
+        next_house_id: u64,
         // Gift vault used to put gifts under the houses trees
         gifts: Vault
     }
@@ -19,46 +38,179 @@ blueprint! {
                             .metadata("name", "Gift")
                             .initial_supply(8000);
 
-            // Instantiate the 10 house components
-            let mut houses: Vec<ComponentAddress> = Vec::new();
-            let mut keys: Vec<Vault> = Vec::new();
-
-            for _ in 0..10 {
-                let (component, key) = House::new(gifts.resource_address());
-                houses.push(component);
-                keys.push(Vault::with_bucket(key));
-            }
-
             Self {
-                houses: houses,
-                keys: keys,
+                houses: HashMap::new(),
+                keys: HashMap::new(),
+                next_house_id: 0,
                 gifts: Vault::with_bucket(gifts)
             }
             .instantiate().globalize()
         }
 
-        // Take the milk and cookies from the house at the specified index.
+        // Allow homeowners to register their house.
+        // The homeowner deposits the house's key so that Santa can come in.
+        // Returns the id of the house in the registry.
+        pub fn register_house(&mut self, house_address: ComponentAddress, key: Bucket, owner_badge: Proof) -> u64 {
+            // Only accept houses instantiated from this package's House blueprint,
+            // other components could lie about their badges or abort Santa's route
+            let component = borrow_component!(house_address);
+            assert!(
+                component.package_address() == Runtime::package_address() && component.blueprint_name() == "House",
+                "This is not a house !"
+            );
+
+            let house: House = house_address.into();
+            assert!(owner_badge.resource_address() == house.get_owner_badge(), "You are not the owner of this house !");
+            assert!(key.resource_address() == house.get_key_resource(), "This key does not open this house !");
+            assert!(!self.houses.values().any(|address| *address == house_address), "This house is already registered !");
+            owner_badge.drop();
+
+            let house_id = self.next_house_id;
+            self.next_house_id += 1;
+
+            self.houses.insert(house_id, house_address);
+            self.keys.insert(house_id, Vault::with_bucket(key));
+
+            info!("House registered with id {}", house_id);
+            house_id
+        }
+
+        // Allow homeowners to remove their house from the registry.
+        // The key is given back to the homeowner.
+        pub fn deregister_house(&mut self, house_id: u64, owner_badge: Proof) -> Bucket {
+            let house_address = match self.houses.get(&house_id) {
+                Some(house_address) => *house_address,
+                None => {
+                    info!("Invalid house id !");
+                    std::process::abort();
+                }
+            };
+
+            let house: House = house_address.into();
+            assert!(owner_badge.resource_address() == house.get_owner_badge(), "You are not the owner of this house !");
+            owner_badge.drop();
+
+            self.houses.remove(&house_id);
+            self.keys.remove(&house_id).unwrap().take_all()
+        }
+
+        // Allow homeowners to give Santa the new key of their house
+        // after rotating it. The old key, now useless, is given back.
+        pub fn update_house_key(&mut self, house_id: u64, new_key: Bucket, owner_badge: Proof) -> Bucket {
+            let house_address = match self.houses.get(&house_id) {
+                Some(house_address) => *house_address,
+                None => {
+                    info!("Invalid house id !");
+                    std::process::abort();
+                }
+            };
+
+            let house: House = house_address.into();
+            assert!(owner_badge.resource_address() == house.get_owner_badge(), "You are not the owner of this house !");
+            assert!(new_key.resource_address() == house.get_key_resource(), "This key does not open this house !");
+            owner_badge.drop();
+
+            let old_key = self.keys.remove(&house_id).unwrap().take_all();
+            self.keys.insert(house_id, Vault::with_bucket(new_key));
+            old_key
+        }
+
+        // List the registered houses with their ids
+        pub fn list_houses(&self) -> Vec<(u64, ComponentAddress)> {
+            let mut houses: Vec<(u64, ComponentAddress)> = self.houses.iter()
+                .map(|(house_id, house_address)| (*house_id, *house_address))
+                .collect();
+            houses.sort_by_key(|(house_id, _)| *house_id);
+            houses
+        }
+
+        // Used by homeowners to instantiate their House
+        pub fn get_gift_resource(&self) -> ResourceAddress {
+            self.gifts.resource_address()
+        }
+
+        // Visit every house of the route in a single call.
+        // Returns the milk and cookies collected, merged by resource,
+        // along with the outcome of the visit for each house of the route.
+        pub fn deliver_route(&mut self, house_ids: Vec<u64>) -> (Vec<Bucket>, Vec<(u64, DeliveryOutcome)>) {
+            let mut collected: Vec<Bucket> = Vec::new();
+            let mut outcomes: Vec<(u64, DeliveryOutcome)> = Vec::new();
+
+            for house_id in house_ids {
+                let (house_address, key) = match (self.houses.get(&house_id), self.keys.get(&house_id)) {
+                    (Some(house_address), Some(key)) => (*house_address, key),
+                    _ => {
+                        // Don't abort the whole route for an unknown house
+                        outcomes.push((house_id, DeliveryOutcome::MissingKey));
+                        continue;
+                    }
+                };
+
+                let house: House = house_address.into();
+                if key.resource_address() != house.get_key_resource() {
+                    outcomes.push((house_id, DeliveryOutcome::MissingKey));
+                    continue;
+                }
+
+                if !house.has_snacks() {
+                    outcomes.push((house_id, DeliveryOutcome::SkippedEmpty));
+                    continue;
+                }
+
+                // Don't revert the houses already visited when the bag is empty
+                if self.gifts.amount() < Decimal::one() {
+                    outcomes.push((house_id, DeliveryOutcome::OutOfGifts));
+                    continue;
+                }
+
+                let rejected = house.give_gift(self.gifts.take(1), key.create_proof());
+                let (cookies, milk) = house.get_milk_and_cookie(key.create_proof());
+
+                // Put the refused gift back in Santa's bag
+                let outcome = if rejected.is_empty() {
+                    DeliveryOutcome::Delivered
+                } else {
+                    DeliveryOutcome::GiftRejected
+                };
+                self.gifts.put(rejected);
+
+                // Merge the buckets with the ones of the same resource
+                for bucket in vec![cookies, milk] {
+                    match collected.iter_mut().find(|b| b.resource_address() == bucket.resource_address()) {
+                        Some(existing) => existing.put(bucket),
+                        None => collected.push(bucket)
+                    }
+                }
+
+                outcomes.push((house_id, outcome));
+            }
+
+            (collected, outcomes)
+        }
+
+        // Take the milk and cookies from the house with the specified id.
         // Then put a gift under the house's Christmas tree
-        pub fn go_into_house(&mut self, house_index: usize) -> (Bucket, Bucket) {
+        pub fn go_into_house(&mut self, house_id: u64) -> (Bucket, Bucket) {
 
-            let (cookies, milk) = match self.houses.get(house_index) {
+            let (cookies, milk) = match self.houses.get(&house_id) {
                 Some(house_address) => {
                     let house: House = (*house_address).into();
 
-                    // Get the key and use the authorize method.
-                    // The authorize method takes a bucket from the key vault,
-                    // create a proof of its content and make it available in the auth zone.
-                    self.keys.get(house_index).unwrap().authorize(|| {
-                        // Put gift under the tree
-                        house.give_gift(self.gifts.take(1));
+                    // Get the key and create proofs of it.
+                    // The house checks that the proof matches its current key.
+                    let key = self.keys.get(&house_id).unwrap();
+
+                    // Put gift under the tree.
+                    // The homeowner might refuse it, put it back in the bag.
+                    let rejected = house.give_gift(self.gifts.take(1), key.create_proof());
+                    self.gifts.put(rejected);
 
-                        // Take and return the cookies and milk
-                        house.get_milk_and_cookie()
-                    })
+                    // Take and return the cookies and milk
+                    house.get_milk_and_cookie(key.create_proof())
                 },
                 None => {
-                    // House not found with the provided house_index
-                    info!("Invalid house index !");
+                    // House not found with the provided house_id
+                    info!("Invalid house id !");
                     std::process::abort();
                 }
             };